@@ -1,16 +1,34 @@
 //! Bubble sort. O(n^2).
 
+use std::cmp::Ordering;
+
+use crate::probe::{self, NoProbe, Probe};
+
 /// Bubble sort "left-right" implementation.
 ///
 /// "Left-right" means smaller values "bubble" to the left.
 pub fn bubble_sort_rl<T: PartialOrd + Clone>(src: &mut [T]) {
+    bubble_sort_rl_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [bubble_sort_rl](fn.bubble_sort_rl.html), but elements are compared by `compare`.
+pub fn bubble_sort_rl_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    bubble_sort_rl_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [bubble_sort_rl](fn.bubble_sort_rl.html), but elements are compared by keys extracted with `key`.
+pub fn bubble_sort_rl_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    bubble_sort_rl_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn bubble_sort_rl_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
     if src.len() <= 1 {
         return;
     }
     for i in 0..src.len() - 1 {
         for j in (i + 1..src.len()).rev() {
-            if src[j] < src[j - 1] {
-                src.swap(j, j - 1);
+            if is_less(&src[j], &src[j - 1]) {
+                probe::swap(src, j, j - 1, probe);
             }
         }
     }
@@ -20,13 +38,27 @@ pub fn bubble_sort_rl<T: PartialOrd + Clone>(src: &mut [T]) {
 ///
 /// "right-left"  means biggest values "bubble" to the right.
 pub fn bubble_sort_lr<T: PartialOrd + Clone>(src: &mut [T]) {
+    bubble_sort_lr_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [bubble_sort_lr](fn.bubble_sort_lr.html), but elements are compared by `compare`.
+pub fn bubble_sort_lr_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    bubble_sort_lr_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [bubble_sort_lr](fn.bubble_sort_lr.html), but elements are compared by keys extracted with `key`.
+pub fn bubble_sort_lr_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    bubble_sort_lr_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn bubble_sort_lr_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
     if src.len() <= 1 {
         return;
     }
     for i in (1..src.len()).rev() {
         for j in 0..i {
-            if src[j] > src[j + 1] {
-                src.swap(j, j + 1);
+            if is_less(&src[j + 1], &src[j]) {
+                probe::swap(src, j, j + 1, probe);
             }
         }
     }
@@ -34,8 +66,10 @@ pub fn bubble_sort_lr<T: PartialOrd + Clone>(src: &mut [T]) {
 
 #[test]
 fn bubble_sort_test() {
-    use crate::test_utils::test_sorting_algorithm;
+    use crate::test_utils::{test_sorting_algorithm, test_sorting_algorithm_by};
 
     assert!(test_sorting_algorithm(bubble_sort_lr).is_ok());
     assert!(test_sorting_algorithm(bubble_sort_rl).is_ok());
+    assert!(test_sorting_algorithm_by(bubble_sort_lr_by, bubble_sort_lr_by_key).is_ok());
+    assert!(test_sorting_algorithm_by(bubble_sort_rl_by, bubble_sort_rl_by_key).is_ok());
 }
@@ -0,0 +1,120 @@
+//! Bucket sort. O(n) in the average case, if the input is drawn from a uniform distribution. In the worst case - Θ(n^2).
+//!
+//! Bucket sort assumes that the input values are uniformly and independently distributed over the `[0, 1)` interval. The interval is divided
+//! into *n* equal-sized subintervals, i.e. buckets, and the input values are distributed into them. As long as values are uniformly distributed,
+//! we don't expect many of them to fall into each bucket. To produce the output, we sort values in each bucket and then go through the buckets
+//! in order, listing the elements in each.
+//!
+//! Buckets are sorted with [insertion sort](../insertion_sort/index.html), which is quadratic. However, the expected size of a bucket is O(1),
+//! so the expected time of sorting all the buckets is O(n) (see CLRS 8.4 for the proof). If all the values fall into the same bucket,
+//! we have insertion sort worst case Θ(n^2).
+
+use std::error::Error;
+use std::fmt;
+
+use num::Float;
+
+use crate::insertion_sort::insertion_sort_impl;
+use crate::probe::{self, NoProbe, Probe};
+
+/// Error returned by bucket sort functions. `src` stays untouched in case of the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketSortError {
+    /// Value (or its normalized key) at the index is NaN, which can't be placed to any bucket.
+    NaN(usize),
+    /// Value (or its normalized key) at the index is not in the `[0, 1)` interval.
+    OutOfRange(usize),
+}
+
+impl fmt::Display for BucketSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BucketSortError::NaN(idx) => write!(f, "value at index {} is NaN", idx),
+            BucketSortError::OutOfRange(idx) => write!(f, "value at index {} is out of [0, 1) range", idx),
+        }
+    }
+}
+
+impl Error for BucketSortError {}
+
+/// Bucket sort of floating point values in the `[0, 1)` interval.
+///
+/// Returns an error if there is a NaN or out of range value in `src`.
+pub fn bucket_sort<T: Float>(src: &mut [T]) -> Result<(), BucketSortError> {
+    bucket_sort_by(src, |value| value.to_f64().unwrap_or(f64::NAN))
+}
+
+/// Bucket sort of any values, which are mapped to the `[0, 1)` interval by `normalize`.
+///
+/// `normalize` must be monotonic: if `a < b`, then `normalize(a) <= normalize(b)`. Otherwise values won't be sorted, because elements of
+/// different buckets are never compared. For example, `|&x| (x - min) / (max - min + 1.0)` normalizes values of the known `min..=max` range.
+///
+/// Returns an error if there is a value, normalized key of which is NaN or is out of range.
+pub fn bucket_sort_by<T: PartialOrd + Clone, F: Fn(&T) -> f64>(src: &mut [T], normalize: F) -> Result<(), BucketSortError> {
+    bucket_sort_by_impl(src, normalize, &NoProbe)
+}
+
+pub(crate) fn bucket_sort_by_impl<T: PartialOrd + Clone, F: Fn(&T) -> f64, P: Probe<T> + ?Sized>(
+    src: &mut [T],
+    normalize: F,
+    probe: &P,
+) -> Result<(), BucketSortError> {
+    let n = src.len();
+    let mut bucket_indices = Vec::with_capacity(n);
+    for (idx, value) in src.iter().enumerate() {
+        let key = normalize(value);
+        if key.is_nan() {
+            return Err(BucketSortError::NaN(idx));
+        }
+        if !(0.0..1.0).contains(&key) {
+            return Err(BucketSortError::OutOfRange(idx));
+        }
+        // `key * n` can be rounded up to `n` for `key` close to 1
+        bucket_indices.push(((key * n as f64) as usize).min(n - 1));
+    }
+
+    let mut buckets = vec![Vec::new(); n];
+    for (value, &bucket_idx) in src.iter().zip(&bucket_indices) {
+        probe.cloned(value);
+        let bucket: &mut Vec<T> = &mut buckets[bucket_idx];
+        bucket.push(value.clone());
+        probe.write(&bucket[bucket.len() - 1]);
+    }
+    for bucket in buckets.iter_mut() {
+        insertion_sort_impl(bucket, &mut probe::probed_lt(probe), probe);
+    }
+    for (slot, value) in src.iter_mut().zip(buckets.into_iter().flatten()) {
+        *slot = value;
+        probe.write(slot);
+    }
+    Ok(())
+}
+
+#[test]
+fn bucket_sort_test() {
+    use crate::test_utils::get_test_vectors;
+
+    let mut src = [0.78, 0.17, 0.39, 0.26, 0.72, 0.94, 0.21, 0.12, 0.23, 0.68];
+    assert!(bucket_sort(&mut src).is_ok());
+    assert_eq!(src, [0.12, 0.17, 0.21, 0.23, 0.26, 0.39, 0.68, 0.72, 0.78, 0.94]);
+
+    let mut src = [0.5f32, 0.0, 0.999_999_9, 0.5, 0.25];
+    assert!(bucket_sort(&mut src).is_ok());
+    assert_eq!(src, [0.0, 0.25, 0.5, 0.5, 0.999_999_9]);
+
+    let mut src = [0.5, f64::NAN, 0.1];
+    assert_eq!(bucket_sort(&mut src), Err(BucketSortError::NaN(1)));
+    assert_eq!(src[0], 0.5);
+
+    let mut src = [0.5, 0.1, 1.0];
+    assert_eq!(bucket_sort(&mut src), Err(BucketSortError::OutOfRange(2)));
+    let mut src = [-0.5, 0.1];
+    assert_eq!(bucket_sort(&mut src), Err(BucketSortError::OutOfRange(0)));
+
+    for (input, sorted) in get_test_vectors().iter_mut() {
+        let min = input.iter().copied().min().unwrap_or(0) as f64;
+        let max = input.iter().copied().max().unwrap_or(0) as f64;
+        assert!(bucket_sort_by(input, |&x| (x as f64 - min) / (max - min + 1.0)).is_ok());
+        assert_eq!(input, sorted);
+    }
+}
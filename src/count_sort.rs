@@ -15,28 +15,119 @@
 //! Should be mentioned that it is a [stable](https://en.wikipedia.org/wiki/Sorting_algorithm#Stability) sorting algorithm, which is an important feature
 //! for some other algorithms that can use count sort, for example, [radix sort](../radix_sort/index.html).
 
-use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
 
-use num::FromPrimitive;
+use num::{PrimInt, ToPrimitive};
+
+use crate::probe::{NoProbe, Probe};
+use crate::select::min_max_by;
+
+/// Ranges of up to this number of keys are accepted for any input length.
+pub const MIN_RANGE_LIMIT: usize = 1 << 16;
+
+/// For larger ranges the number of keys shouldn't exceed `RANGE_FACTOR` times the input length, otherwise *k = O(n)* doesn't hold.
+pub const RANGE_FACTOR: usize = 16;
+
+/// Error returned by [count_sort](fn.count_sort.html) and [count_sort_by_key](fn.count_sort_by_key.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountSortError {
+    /// `max - min + 1` keys (`max_key + 1` for [count_sort_by_key](fn.count_sort_by_key.html)) are too much for the input of length `len`.
+    /// `src` stays untouched.
+    ///
+    /// Counting over such range wastes memory and time, so some comparison sort should be used instead.
+    RangeTooLarge { len: usize, limit: usize },
+}
+
+impl fmt::Display for CountSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CountSortError::RangeTooLarge { len, limit } => {
+                write!(f, "range of values exceeds {} keys allowed for the input of length {}", limit, len)
+            }
+        }
+    }
+}
+
+impl Error for CountSortError {}
 
 /// Count sort entry function
 ///
-/// If `src` length is greater than 1 and its inner data has a primitive type, **instances** of which are in the range of `usize`, then the sort will be performed.
-/// Otherwise no mutations will be made on `src`.
-pub fn count_sort<T: FromPrimitive + TryInto<usize> + Ord + Copy>(src: &mut [T]) {
-    let min_element = src.iter().min().copied().map(TryInto::<usize>::try_into).map(Result::ok).flatten();
-    let max_element = src.iter().max().copied();
-    if min_element.is_some() && src.len() > 1 {
+/// Keys are counted over the `min..=max` range of `src` values: each value is stored with the `value - min` offset. So negative values are sorted
+/// as well, and values clustered far from zero (e.g. `1_000_000..1_000_100`) don't waste memory on counters for `0..min`.
+///
+/// Returns [CountSortError::RangeTooLarge](enum.CountSortError.html#variant.RangeTooLarge) without mutating `src`, if the range has more than
+/// `max(MIN_RANGE_LIMIT, RANGE_FACTOR * src.len())` keys.
+pub fn count_sort<T: PrimInt + Ord + Copy>(src: &mut [T]) -> Result<(), CountSortError> {
+    count_sort_probed(src, &NoProbe)
+}
+
+/// Same as [count_sort](fn.count_sort.html), but operations are reported to `probe`. Comparisons are made only to find the range of values.
+pub(crate) fn count_sort_probed<T: PrimInt + Ord + Copy, P: Probe<T> + ?Sized>(src: &mut [T], probe: &P) -> Result<(), CountSortError> {
+    if src.len() <= 1 {
+        // `src` of length 1 is sorted
+        return Ok(());
+    }
+    let ((_, &min_element), (_, &max_element)) = min_max_by(src, |a, b| {
+        probe.compare(a, b);
+        a.cmp(b)
+    })
+    .expect("there is at least one element in src");
+
+    let limit = range_limit(src.len());
+    match offset(max_element, min_element) {
+        Some(max_key) if max_key < limit => {
+            count_sort_impl(src, min_element, max_key, probe);
+            Ok(())
+        }
+        _ => Err(CountSortError::RangeTooLarge { len: src.len(), limit }),
+    }
+}
+
+/// Returns the largest number of keys accepted for the input of length `len`.
+fn range_limit(len: usize) -> usize {
+    MIN_RANGE_LIMIT.max(RANGE_FACTOR.saturating_mul(len))
+}
+
+/// Returns `value - min` if it fits `usize`.
+///
+/// Values are converted to `i128`, so the difference doesn't overflow for signed types, e.g. `127i8 - (-128i8)`.
+/// The only type, values of which don't fit `i128`, is `u128`, but its values are non-negative.
+fn offset<T: PrimInt>(value: T, min: T) -> Option<usize> {
+    match (value.to_i128(), min.to_i128()) {
+        (Some(value), Some(min)) => value.checked_sub(min).and_then(|offset| offset.to_usize()),
+        _ => value.to_u128().zip(min.to_u128()).and_then(|(value, min)| (value - min).to_usize()),
+    }
+}
+
+/// Stable count sort of records by an integer key.
+///
+/// Sorts `src` by the keys returned by `key`, so elements are not required to be numbers: they can be any records carrying
+/// some "satellite" data. Elements with equal keys keep their relative order. The additional space is *O(n + k)*,
+/// where *k* is the largest key, so keys should be small (priority levels, days of week and so on).
+///
+/// Returns [CountSortError::RangeTooLarge](enum.CountSortError.html#variant.RangeTooLarge) without mutating `src`, if there are more than
+/// `max(MIN_RANGE_LIMIT, RANGE_FACTOR * src.len())` keys in `0..=max_key`.
+pub fn count_sort_by_key<R: Clone, F: Fn(&R) -> usize>(src: &mut [R], key: F) -> Result<(), CountSortError> {
+    if src.len() <= 1 {
         // `src` of length 1 is sorted
-        count_sort_impl(src, max_element.expect("there is at least one element in src"))
+        return Ok(());
+    }
+    let max_key = src.iter().map(&key).max().expect("there is at least one element in src");
+    let limit = range_limit(src.len());
+    if max_key >= limit {
+        return Err(CountSortError::RangeTooLarge { len: src.len(), limit });
     }
+    count_sort_by_key_impl(src, max_key, key, &NoProbe);
+    Ok(())
 }
 
 /// Count sort implementation
 ///
 /// Because of conditions stated in the module [doc](index.html) we parametrize function with a number type value.
-/// Unfortunately this algorithm requires *O(max_element)* additional space, which is a good example of space - time tradeoff.
-/// 
+/// Unfortunately this algorithm requires *O(max_element - min_element)* additional space, which is a good example of space - time tradeoff.
+/// Key of a value is its offset from `min_element`, so the value of the key is rebuilt as `min_element + key`.
+///
 /// A closer to CLRS version:
 /// ```rust
 /// # use std::convert::TryInto;
@@ -68,30 +159,61 @@ pub fn count_sort<T: FromPrimitive + TryInto<usize> + Ord + Copy>(src: &mut [T])
 ///     src.copy_from_slice(&sorted[..])
 /// }
 /// ```
-fn count_sort_impl<T: FromPrimitive + TryInto<usize> + Ord + Copy>(src: &mut [T], max_element: T) {
-     let max_element = max_element
-        .try_into()
-        .ok()
-        .expect("this fn is callable for types, that can be converted to usize");
-    let mut keys_count: Vec<Option<usize>> = vec![None; max_element + 1];
-    for &key in &src[..] {
-        if let Ok(key) = key.try_into() {
-            keys_count[key] = keys_count[key].or(Some(0)).map(|count| count + 1);
-        }
+fn count_sort_impl<T: PrimInt + Ord + Copy, P: Probe<T> + ?Sized>(src: &mut [T], min_element: T, max_key: usize, probe: &P) {
+    let mut keys_count: Vec<Option<usize>> = vec![None; max_key + 1];
+    for &value in &src[..] {
+        let key = offset(value, min_element).expect("value is in min..=max range");
+        keys_count[key] = keys_count[key].or(Some(0)).map(|count| count + 1);
     }
 
     let mut key = 0;
+    let mut value = min_element;
     let mut src_idx = 0;
     while key < keys_count.len() {
         let count = keys_count[key];
         if let Some(mut count) = count {
             while count != 0 {
-                src[src_idx] = T::from_usize(key).expect("key was derived from T");
+                src[src_idx] = value;
+                probe.write(&src[src_idx]);
                 src_idx += 1;
                 count -= 1;
             }
         }
         key += 1;
+        if key < keys_count.len() {
+            // `value` is `min_element + key`. Not incrementing after the last key, because `max_element + 1` can overflow `T`.
+            value = value + T::one();
+        }
+    }
+}
+
+/// Stable count sort of `src` by the key derived with `key` function. Each key must be in the range of `0..=max_key`.
+///
+/// Unlike [count_sort_impl](fn.count_sort_impl.html) values aren't rebuilt from keys, but are placed to their positions in the sorted copy of `src`.
+/// That's why elements can carry any data besides the key. The placement is done as in CLRS: after counting keys, `keys_count[key]` is turned to
+/// the number of elements with keys less or equal than `key`, so it's the position after the last element with `key` in the sorted output.
+/// `src` is traversed from the end to the beginning, so elements with equal keys keep their relative order, i.e. the sort is stable.
+/// That's the feature [radix sort](../radix_sort/index.html) relies on, when it uses count sort to sort numbers by digits.
+pub(crate) fn count_sort_by_key_impl<R: Clone, F: Fn(&R) -> usize, P: Probe<R> + ?Sized>(src: &mut [R], max_key: usize, key: F, probe: &P) {
+    let mut keys_count: Vec<usize> = vec![0; max_key + 1];
+    for element in src.iter() {
+        keys_count[key(element)] += 1;
+    }
+    for idx in 1..keys_count.len() {
+        keys_count[idx] += keys_count[idx - 1];
+    }
+
+    let mut sorted: Vec<Option<R>> = vec![None; src.len()];
+    for element in src.iter().rev() {
+        let key = key(element);
+        keys_count[key] -= 1;
+        probe.cloned(element);
+        let slot = sorted[keys_count[key]].insert(element.clone());
+        probe.write(slot);
+    }
+    for (slot, element) in src.iter_mut().zip(sorted) {
+        *slot = element.expect("each position of the sorted output is filled");
+        probe.write(slot);
     }
 }
 
@@ -100,12 +222,76 @@ fn count_sort_test() {
     use crate::test_utils::get_test_vectors;
 
     for (input, sorted) in get_test_vectors().iter_mut() {
-        count_sort(input);
+        assert!(count_sort(input).is_ok());
+        assert_eq!(input, sorted);
+    }
+
+    let mut clustered: Vec<u64> = (1_000_000..1_000_100).rev().collect();
+    assert!(count_sort(&mut clustered).is_ok());
+    assert!(clustered.iter().copied().eq(1_000_000..1_000_100));
+
+    let mut extremes = [i8::MAX, 0, i8::MIN, -1, i8::MAX, 1];
+    assert!(count_sort(&mut extremes).is_ok());
+    assert_eq!(extremes, [i8::MIN, -1, 0, 1, i8::MAX, i8::MAX]);
 
+    let mut sparse = [i64::MAX, 0, i64::MIN];
+    let limit = MIN_RANGE_LIMIT;
+    assert_eq!(count_sort(&mut sparse), Err(CountSortError::RangeTooLarge { len: 3, limit }));
+    assert_eq!(sparse, [i64::MAX, 0, i64::MIN]);
+
+    let mut huge = [u128::MAX, u128::MAX - 1, 0];
+    assert!(count_sort(&mut huge).is_err());
+}
+
+#[test]
+fn count_sort_by_key_impl_stability_test() {
+    // sorting by the last digit only
+    let mut src = [(21, 'a'), (10, 'b'), (31, 'c'), (20, 'd'), (11, 'e'), (22, 'f')];
+    count_sort_by_key_impl(&mut src, 9, |&(num, _)| num % 10, &NoProbe);
+    assert_eq!(src, [(10, 'b'), (20, 'd'), (21, 'a'), (31, 'c'), (11, 'e'), (22, 'f')]);
+}
+
+#[test]
+fn count_sort_by_key_test() {
+    use crate::test_utils::get_test_vectors;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Task {
+        priority: usize,
+        name: &'static str,
+    }
+
+    let mut tasks = vec![
+        Task { priority: 2, name: "deploy" },
+        Task { priority: 0, name: "fix" },
+        Task { priority: 1, name: "review" },
+        Task { priority: 2, name: "test" },
+        Task { priority: 0, name: "hotfix" },
+        Task { priority: 1, name: "plan" },
+    ];
+    assert!(count_sort_by_key(&mut tasks, |task| task.priority).is_ok());
+    let names: Vec<_> = tasks.iter().map(|task| task.name).collect();
+    assert_eq!(names, ["fix", "hotfix", "review", "plan", "deploy", "test"]);
+
+    for (input, sorted) in get_test_vectors().iter_mut() {
         if input.iter().any(|&v| v < 0) {
             continue;
         }
-
+        assert!(count_sort_by_key(input, |&v| v as usize).is_ok());
         assert_eq!(input, sorted);
     }
+
+    // keys are counted over `0..=max_key`, so a single large key is too much
+    let mut sparse = [(usize::MAX, 'a'), (0, 'b')];
+    let limit = MIN_RANGE_LIMIT;
+    assert_eq!(
+        count_sort_by_key(&mut sparse, |&(key, _)| key),
+        Err(CountSortError::RangeTooLarge { len: 2, limit })
+    );
+    assert_eq!(sparse, [(usize::MAX, 'a'), (0, 'b')]);
+    let mut large = [MIN_RANGE_LIMIT, 0];
+    assert!(count_sort_by_key(&mut large, |&key| key).is_err());
+    let mut largest = [MIN_RANGE_LIMIT - 1, 0];
+    assert!(count_sort_by_key(&mut largest, |&key| key).is_ok());
+    assert_eq!(largest, [0, MIN_RANGE_LIMIT - 1]);
 }
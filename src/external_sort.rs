@@ -0,0 +1,671 @@
+//! External merge sort for inputs, which don't fit in memory.
+//!
+//! Sorting algorithms of the other modules need the whole input in memory. External sort reads the input sequentially and works in two phases:
+//! 1. The input is read in chunks, which fit in the memory budget. Each chunk is sorted by one of the in-memory [sorters](../sorter/index.html)
+//!    and written to a temporary file as a sorted *run*.
+//! 2. Runs are merged by *k* at once (*k* is the fan-in) with a min-heap of their current records, so only *k* records and I/O buffers are
+//!    in memory. The heap is maintained by the same sift-down as [heap sort](../heap_sort/index.html) uses. While there are more than *k*
+//!    runs, merged runs are written to new temporary files, the last merge writes to the output.
+//!
+//! Each record is read and written once to create runs and once per merge pass, and there are ⌈log_k(runs)⌉ passes. So a larger fan-in means less I/O,
+//! but smaller I/O buffers, because they share the memory budget.
+//!
+//! Records are read and written by a [RecordFormat](trait.RecordFormat.html): [Lines](struct.Lines.html) for newline-delimited text
+//! and [Integers](struct.Integers.html) for binary fixed-width integers.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::heap_sort::{build_max_heap, max_heapify};
+use crate::probe::NoProbe;
+use crate::sorter::{SortError, Sorter};
+
+/// Memory budget of [ExternalSort::new](struct.ExternalSort.html#method.new), 64 MiB.
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 << 20;
+
+/// Fan-in of [ExternalSort::new](struct.ExternalSort.html#method.new).
+pub const DEFAULT_FAN_IN: usize = 16;
+
+/// Error returned by [ExternalSort::sort](struct.ExternalSort.html#method.sort).
+#[derive(Debug)]
+pub enum ExternalSortError {
+    /// Fan-in is less than 2, so runs can't be merged.
+    FanInTooSmall(usize),
+    /// Reading the input, writing the output or temporary files failed, or the input is malformed.
+    Io(io::Error),
+    /// The in-memory sort of a chunk failed.
+    Sort(SortError),
+}
+
+impl fmt::Display for ExternalSortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalSortError::FanInTooSmall(fan_in) => write!(f, "fan-in {} is less than 2", fan_in),
+            ExternalSortError::Io(err) => write!(f, "I/O failed: {}", err),
+            ExternalSortError::Sort(err) => write!(f, "sorting a chunk failed: {}", err),
+        }
+    }
+}
+
+impl Error for ExternalSortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExternalSortError::FanInTooSmall(_) => None,
+            ExternalSortError::Io(err) => Some(err),
+            ExternalSortError::Sort(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ExternalSortError {
+    fn from(err: io::Error) -> Self {
+        ExternalSortError::Io(err)
+    }
+}
+
+impl From<SortError> for ExternalSortError {
+    fn from(err: SortError) -> Self {
+        ExternalSortError::Sort(err)
+    }
+}
+
+/// Format of records in the input, the output and temporary files.
+pub trait RecordFormat {
+    type Record: Ord;
+
+    /// Reads the next record, returns `None` at the end of the input.
+    fn read<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<Self::Record>>;
+
+    /// Writes `record`, so that [read](#tymethod.read) returns it back.
+    fn write<W: Write>(&self, writer: &mut W, record: &Self::Record) -> io::Result<()>;
+
+    /// Returns the approximate memory taken by `record`, which is counted against the memory budget.
+    fn memory_size(&self, _record: &Self::Record) -> usize {
+        mem::size_of::<Self::Record>()
+    }
+}
+
+/// Newline-delimited records compared as bytes, like `LC_ALL=C sort` does.
+///
+/// Records don't include `\n`. The last record may be not terminated, but each record is terminated in the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lines;
+
+impl RecordFormat for Lines {
+    type Record = Vec<u8>;
+
+    fn read<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, record: &Vec<u8>) -> io::Result<()> {
+        writer.write_all(record)?;
+        writer.write_all(b"\n")
+    }
+
+    fn memory_size(&self, record: &Vec<u8>) -> usize {
+        mem::size_of::<Vec<u8>>() + record.len()
+    }
+}
+
+/// Byte order of binary integers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+/// Primitive integer, which is stored in a fixed number of bytes.
+pub trait FixedWidthInteger: Ord + Sized {
+    /// Number of bytes.
+    const WIDTH: usize;
+
+    /// Converts `WIDTH` bytes to the integer.
+    fn from_bytes(bytes: &[u8], byte_order: ByteOrder) -> Self;
+
+    /// Writes `WIDTH` bytes of the integer.
+    fn write_bytes<W: Write>(&self, writer: &mut W, byte_order: ByteOrder) -> io::Result<()>;
+}
+
+macro_rules! impl_fixed_width_integer {
+    ($($int:ty),*) => {
+        $(
+            impl FixedWidthInteger for $int {
+                const WIDTH: usize = mem::size_of::<$int>();
+
+                fn from_bytes(bytes: &[u8], byte_order: ByteOrder) -> Self {
+                    let mut array = [0; mem::size_of::<$int>()];
+                    array.copy_from_slice(bytes);
+                    match byte_order {
+                        ByteOrder::LittleEndian => <$int>::from_le_bytes(array),
+                        ByteOrder::BigEndian => <$int>::from_be_bytes(array),
+                    }
+                }
+
+                fn write_bytes<W: Write>(&self, writer: &mut W, byte_order: ByteOrder) -> io::Result<()> {
+                    match byte_order {
+                        ByteOrder::LittleEndian => writer.write_all(&self.to_le_bytes()),
+                        ByteOrder::BigEndian => writer.write_all(&self.to_be_bytes()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_width_integer!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Binary integers of type `T` written one after another without separators.
+///
+/// Reading fails with [io::ErrorKind::InvalidData](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData),
+/// if the input length isn't a multiple of the integer width.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Integers<T> {
+    pub byte_order: ByteOrder,
+    marker: PhantomData<T>,
+}
+
+impl<T> Integers<T> {
+    pub fn new(byte_order: ByteOrder) -> Self {
+        Integers {
+            byte_order,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: FixedWidthInteger> RecordFormat for Integers<T> {
+    type Record = T;
+
+    fn read<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<T>> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        // the widest integer is `u128`
+        let mut bytes = [0; 16];
+        let bytes = &mut bytes[..T::WIDTH];
+        reader.read_exact(bytes).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(io::ErrorKind::InvalidData, "input length isn't a multiple of the integer width"),
+            _ => err,
+        })?;
+        Ok(Some(T::from_bytes(bytes, self.byte_order)))
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, record: &T) -> io::Result<()> {
+        record.write_bytes(writer, self.byte_order)
+    }
+}
+
+/// Numbers describing the work of an external sort.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExternalSortStats {
+    pub records: u64,
+    /// Sorted runs written to temporary files by the first phase, 0 if the input fits in the memory budget.
+    pub runs: usize,
+    /// *k*-way merges, including the last one to the output.
+    pub merges: usize,
+}
+
+/// External merge sort, see the [module](index.html) docs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExternalSort {
+    /// Directory for temporary files with runs. Files are removed as soon as they are merged, or if the sort fails.
+    pub temp_dir: PathBuf,
+    /// Approximate number of bytes taken by records of a chunk, or by I/O buffers while merging.
+    ///
+    /// The in-memory sort of a chunk can take more, e.g. [MergeSort](../sorter/struct.MergeSort.html) allocates a buffer for a half of the chunk,
+    /// while [HeapSort](../sorter/struct.HeapSort.html) doesn't allocate at all.
+    pub memory_budget: usize,
+    /// Maximum number of runs merged at once, at least 2.
+    pub fan_in: usize,
+}
+
+impl ExternalSort {
+    /// Returns the sort with [DEFAULT_MEMORY_BUDGET](constant.DEFAULT_MEMORY_BUDGET.html) and [DEFAULT_FAN_IN](constant.DEFAULT_FAN_IN.html).
+    pub fn new<P: Into<PathBuf>>(temp_dir: P) -> Self {
+        ExternalSort {
+            temp_dir: temp_dir.into(),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            fan_in: DEFAULT_FAN_IN,
+        }
+    }
+
+    /// Sorts records of `input` in `format` to `output`. Chunks are sorted by `sorter`.
+    ///
+    /// If `sorter` is stable, the external sort is stable as well: runs are made of consecutive chunks, and equal records
+    /// of earlier runs go first while merging.
+    ///
+    /// ```
+    /// use clrs_algos::external_sort::{ExternalSort, Lines};
+    /// use clrs_algos::sorter::MergeSort;
+    ///
+    /// let input = "pear\napple\nplum\nfig\ncherry\n";
+    /// let mut output = Vec::new();
+    /// let external_sort = ExternalSort {
+    ///     memory_budget: 64,
+    ///     fan_in: 2,
+    ///     ..ExternalSort::new(std::env::temp_dir())
+    /// };
+    /// let stats = external_sort.sort(&Lines, &MergeSort, input.as_bytes(), &mut output).unwrap();
+    /// assert_eq!(output, b"apple\ncherry\nfig\npear\nplum\n");
+    /// assert_eq!((stats.records, stats.runs), (5, 2));
+    /// ```
+    pub fn sort<F: RecordFormat, R: Read, W: Write>(
+        &self,
+        format: &F,
+        sorter: &dyn Sorter<F::Record>,
+        input: R,
+        output: W,
+    ) -> Result<ExternalSortStats, ExternalSortError> {
+        if self.fan_in < 2 {
+            return Err(ExternalSortError::FanInTooSmall(self.fan_in));
+        }
+        let mut input = BufReader::with_capacity(self.io_buffer_size(), input);
+        let mut output = BufWriter::with_capacity(self.io_buffer_size(), output);
+        let mut stats = ExternalSortStats::default();
+
+        let mut runs = Vec::new();
+        let mut chunk = Vec::new();
+        loop {
+            let exhausted = self.read_chunk(format, &mut input, &mut chunk)?;
+            stats.records += chunk.len() as u64;
+            sorter.sort(&mut chunk)?;
+            if exhausted && runs.is_empty() {
+                // the whole input fits in memory, so there is nothing to merge
+                for record in &chunk {
+                    format.write(&mut output, record)?;
+                }
+                output.flush()?;
+                return Ok(stats);
+            }
+            if !chunk.is_empty() {
+                runs.push(self.write_run(format, &chunk)?);
+            }
+            chunk.clear();
+            if exhausted {
+                break;
+            }
+        }
+        // only runs are read from now on, so the chunk and the input buffer don't take the memory budget of merging
+        drop(chunk);
+        drop(input);
+        stats.runs = runs.len();
+
+        // each pass merges groups of consecutive runs, so runs stay in the order of the input, and the sort is stable
+        while runs.len() > self.fan_in {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(self.fan_in));
+            let mut runs_left = runs.into_iter();
+            loop {
+                let group: Vec<Run> = runs_left.by_ref().take(self.fan_in).collect();
+                if group.len() <= 1 {
+                    // the last run has no pair in this pass
+                    merged.extend(group);
+                    break;
+                }
+                let (run, file) = self.create_run()?;
+                let mut writer = BufWriter::with_capacity(self.io_buffer_size(), file);
+                self.merge(format, group, &mut writer)?;
+                writer.flush()?;
+                merged.push(run);
+                stats.merges += 1;
+            }
+            runs = merged;
+        }
+        self.merge(format, runs, &mut output)?;
+        stats.merges += 1;
+        output.flush()?;
+        Ok(stats)
+    }
+
+    /// Same as [sort](#method.sort), but records are read from the file at `input` and written to the file at `output`,
+    /// which is created or truncated. `output` must not be the same file as `input`.
+    pub fn sort_file<F: RecordFormat>(
+        &self,
+        format: &F,
+        sorter: &dyn Sorter<F::Record>,
+        input: &Path,
+        output: &Path,
+    ) -> Result<ExternalSortStats, ExternalSortError> {
+        let input = File::open(input)?;
+        let output = File::create(output)?;
+        self.sort(format, sorter, input, output)
+    }
+
+    /// The memory budget is shared by the heap with up to `fan_in` records, `fan_in` readers of runs, the writer of the merged run
+    /// and the writer of the output, which is created before the first pass. Records are usually much shorter than the buffers,
+    /// so only the buffers are taken into account.
+    fn io_buffer_size(&self) -> usize {
+        (self.memory_budget / (self.fan_in + 2)).max(1)
+    }
+
+    /// Reads records to `chunk` until they take the memory budget. Returns whether the input is exhausted.
+    ///
+    /// At least one record is read, even if it exceeds the budget.
+    fn read_chunk<F: RecordFormat, R: BufRead>(&self, format: &F, input: &mut R, chunk: &mut Vec<F::Record>) -> io::Result<bool> {
+        let mut size = 0;
+        while size < self.memory_budget || chunk.is_empty() {
+            match format.read(input)? {
+                Some(record) => {
+                    size += format.memory_size(&record);
+                    chunk.push(record);
+                }
+                None => return Ok(true),
+            }
+        }
+        // the last chunk can end right at the end of the input
+        Ok(input.fill_buf()?.is_empty())
+    }
+
+    /// Creates a new temporary file for a run.
+    fn create_run(&self) -> io::Result<(Run, File)> {
+        static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("external-sort-{}-{}.run", process::id(), NEXT_RUN.fetch_add(1, Ordering::Relaxed));
+        let path = self.temp_dir.join(name);
+        let file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        Ok((Run { path }, file))
+    }
+
+    fn write_run<F: RecordFormat>(&self, format: &F, chunk: &[F::Record]) -> io::Result<Run> {
+        let (run, file) = self.create_run()?;
+        let mut writer = BufWriter::with_capacity(self.io_buffer_size(), file);
+        for record in chunk {
+            format.write(&mut writer, record)?;
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+
+    /// Merges sorted `runs` to `output` and removes their files.
+    ///
+    /// The heap holds the current record of each run with the index of the run. The least record is written, and it's replaced by the next
+    /// record of its run, or by the last heap element if the run is exhausted, then it's sifted down. Indices make the order total,
+    /// so equal records are written in the order of their runs.
+    fn merge<F: RecordFormat, W: Write>(&self, format: &F, runs: Vec<Run>, output: &mut W) -> io::Result<()> {
+        let mut readers = Vec::with_capacity(runs.len());
+        for run in &runs {
+            readers.push(BufReader::with_capacity(self.io_buffer_size(), File::open(&run.path)?));
+        }
+        let mut heap = Vec::with_capacity(readers.len());
+        for (idx, reader) in readers.iter_mut().enumerate() {
+            if let Some(record) = format.read(reader)? {
+                heap.push((record, idx));
+            }
+        }
+
+        // heap sort builds a max heap, so the order is reversed to have the least record in the root
+        let mut is_greater = |a: &(F::Record, usize), b: &(F::Record, usize)| b < a;
+        if heap.len() > 1 {
+            build_max_heap(&mut heap, &mut is_greater, &NoProbe);
+        }
+        while let Some((record, idx)) = heap.first() {
+            format.write(output, record)?;
+            let idx = *idx;
+            match format.read(&mut readers[idx])? {
+                Some(next) => heap[0].0 = next,
+                None => {
+                    heap.swap_remove(0);
+                }
+            }
+            if !heap.is_empty() {
+                max_heapify(&mut heap, 0, &mut is_greater, &NoProbe);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Temporary file with a sorted run, which is removed on drop.
+#[derive(Debug)]
+struct Run {
+    path: PathBuf,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        // the sort result doesn't depend on the removal, and there is no way to report the failure from `drop`
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn external_sort_integers_test() {
+    use crate::sorter::{comparison_sorters, HeapSort, MergeSort};
+    use crate::test_utils::{get_random_vector, TempDir};
+
+    let temp_dir = TempDir::new("external-sort-integers");
+    let values = get_random_vector(10_000, u32::MAX, 25);
+    let mut sorted = values.clone();
+    sorted.sort_unstable();
+    let format = Integers::<u32>::new(ByteOrder::LittleEndian);
+    let mut input = Vec::new();
+    for value in &values {
+        format.write(&mut input, value).expect("writing to a vector doesn't fail");
+    }
+
+    // 100 runs of 100 integers are merged to 25, 7, 2 and 1 runs, the last of 7 runs is left for the next pass
+    let external_sort = ExternalSort {
+        memory_budget: 100 * 4,
+        fan_in: 4,
+        ..ExternalSort::new(temp_dir.path())
+    };
+    // 4 readers of runs, the writer of the merged run and the writer of the output fit in the budget while merging
+    assert_eq!(external_sort.io_buffer_size(), 66);
+    let mut output = Vec::new();
+    let stats = external_sort.sort(&format, &HeapSort, input.as_slice(), &mut output).expect("sorting succeeds");
+    assert_eq!(
+        stats,
+        ExternalSortStats {
+            records: 10_000,
+            runs: 100,
+            merges: 34
+        }
+    );
+    let output: Vec<u32> = output.chunks(4).map(|bytes| u32::from_bytes(bytes, ByteOrder::LittleEndian)).collect();
+    assert_eq!(output, sorted);
+    assert_eq!(temp_dir.files(), 0, "temporary files are removed");
+
+    // any comparison sorter can sort chunks, the input fitting in the budget isn't written to temporary files,
+    // and the budget less than a record makes a run of each record
+    let input = &input[..1_000 * 4];
+    let mut sorted = values[..1_000].to_vec();
+    sorted.sort_unstable();
+    for sorter in comparison_sorters::<u32>().iter() {
+        for &(memory_budget, runs) in &[(0, 1_000), (400, 10), (4_000, 0)] {
+            let external_sort = ExternalSort {
+                memory_budget,
+                fan_in: 7,
+                ..external_sort.clone()
+            };
+            let mut output = Vec::new();
+            let stats = external_sort.sort(&format, sorter.as_ref(), input, &mut output).expect("sorting succeeds");
+            assert_eq!(stats.runs, runs, "{:?}", sorter);
+            assert!(output
+                .chunks(4)
+                .map(|bytes| u32::from_bytes(bytes, ByteOrder::LittleEndian))
+                .eq(sorted.iter().copied()));
+        }
+    }
+
+    // negative big endian values in files
+    let values: Vec<i64> = get_random_vector(1_001, 2_000, 26).into_iter().map(|value| i64::from(value) - 1_000).collect();
+    let format = Integers::<i64>::new(ByteOrder::BigEndian);
+    let input_path = temp_dir.path().join("input.bin");
+    let output_path = temp_dir.path().join("output.bin");
+    let mut input = Vec::new();
+    for value in &values {
+        format.write(&mut input, value).expect("writing to a vector doesn't fail");
+    }
+    fs::write(&input_path, input).expect("writing the input succeeds");
+    let stats = external_sort
+        .sort_file(&format, &MergeSort, &input_path, &output_path)
+        .expect("sorting succeeds");
+    assert_eq!(stats.runs, 21);
+    let output: Vec<i64> = fs::read(&output_path)
+        .expect("reading the output succeeds")
+        .chunks(8)
+        .map(|bytes| i64::from_bytes(bytes, ByteOrder::BigEndian))
+        .collect();
+    let mut sorted = values;
+    sorted.sort_unstable();
+    assert_eq!(output, sorted);
+    assert_eq!(temp_dir.files(), 2, "only the input and the output are left");
+}
+
+#[test]
+fn external_sort_lines_test() {
+    use crate::sorter::MergeSort;
+    use crate::test_utils::{get_random_vector, TempDir};
+
+    let temp_dir = TempDir::new("external-sort-lines");
+    let lines: Vec<String> = get_random_vector(2_000, 500, 27).into_iter().map(|value| format!("{:x}", value * 7)).collect();
+    // the last line isn't terminated
+    let input = lines.join("\n");
+    let mut sorted = lines;
+    sorted.sort_unstable();
+    let expected: String = sorted.iter().map(|line| format!("{}\n", line)).collect();
+    for &fan_in in &[2, 3, 10, 1_000] {
+        let external_sort = ExternalSort {
+            memory_budget: 512,
+            fan_in,
+            ..ExternalSort::new(temp_dir.path())
+        };
+        let mut output = Vec::new();
+        let stats = external_sort.sort(&Lines, &MergeSort, input.as_bytes(), &mut output).expect("sorting succeeds");
+        assert_eq!(String::from_utf8(output).expect("output is UTF-8"), expected);
+        assert_eq!(stats.records, 2_000);
+        assert!(stats.runs > 50);
+        // each pass merges full groups and a partial one, if it has more than one run
+        let (mut runs, mut merges) = (stats.runs, 1);
+        while runs > fan_in {
+            merges += runs / fan_in + usize::from(runs % fan_in > 1);
+            runs = runs.div_ceil(fan_in);
+        }
+        assert_eq!(stats.merges, merges, "{:?}", stats);
+    }
+    assert_eq!(temp_dir.files(), 0);
+
+    let mut output = Vec::new();
+    let stats = ExternalSort::new(temp_dir.path())
+        .sort(&Lines, &MergeSort, &b""[..], &mut output)
+        .expect("sorting succeeds");
+    assert_eq!((stats, output.len()), (ExternalSortStats::default(), 0));
+    let mut output = Vec::new();
+    ExternalSort::new(temp_dir.path())
+        .sort(&Lines, &MergeSort, &b"b\n\na\n"[..], &mut output)
+        .expect("sorting succeeds");
+    assert_eq!(output, b"\na\nb\n");
+}
+
+#[test]
+fn external_sort_stability_test() {
+    use crate::sorter::MergeSort;
+    use crate::test_utils::{get_random_vector, TempDir};
+
+    /// Integer, which is compared only by its upper half, so equal records are distinguishable.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Keyed(u32);
+
+    impl PartialOrd for Keyed {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Keyed {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            (self.0 >> 16).cmp(&(other.0 >> 16))
+        }
+    }
+
+    struct KeyedFormat;
+
+    impl RecordFormat for KeyedFormat {
+        type Record = Keyed;
+
+        fn read<R: BufRead>(&self, reader: &mut R) -> io::Result<Option<Keyed>> {
+            Ok(Integers::<u32>::new(ByteOrder::LittleEndian).read(reader)?.map(Keyed))
+        }
+
+        fn write<W: Write>(&self, writer: &mut W, record: &Keyed) -> io::Result<()> {
+            record.0.write_bytes(writer, ByteOrder::LittleEndian)
+        }
+    }
+
+    // keys are random, lower halves are the input positions
+    let values: Vec<u32> = get_random_vector(5_000, 20, 28)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, key)| key << 16 | idx as u32)
+        .collect();
+    let mut input = Vec::new();
+    for &value in &values {
+        value
+            .write_bytes(&mut input, ByteOrder::LittleEndian)
+            .expect("writing to a vector doesn't fail");
+    }
+    let temp_dir = TempDir::new("external-sort-stability");
+    let external_sort = ExternalSort {
+        memory_budget: 256,
+        fan_in: 3,
+        ..ExternalSort::new(temp_dir.path())
+    };
+    let mut output = Vec::new();
+    external_sort
+        .sort(&KeyedFormat, &MergeSort, input.as_slice(), &mut output)
+        .expect("sorting succeeds");
+    let mut expected = values;
+    // positions are increasing within keys, so the stable order is the order of values
+    expected.sort_unstable();
+    assert!(output.chunks(4).map(|bytes| u32::from_bytes(bytes, ByteOrder::LittleEndian)).eq(expected));
+}
+
+#[test]
+fn external_sort_errors_test() {
+    use crate::sorter::{CountSort, HeapSort};
+    use crate::test_utils::TempDir;
+
+    let temp_dir = TempDir::new("external-sort-errors");
+    let external_sort = ExternalSort {
+        memory_budget: 16,
+        fan_in: 1,
+        ..ExternalSort::new(temp_dir.path())
+    };
+    let result = external_sort.sort(&Lines, &HeapSort, &b"b\na\n"[..], Vec::new());
+    assert!(matches!(result, Err(ExternalSortError::FanInTooSmall(1))));
+
+    let external_sort = ExternalSort { fan_in: 2, ..external_sort };
+    // 9 bytes aren't a whole number of `u16` values, the error is found after runs are written
+    let format = Integers::<u16>::new(ByteOrder::BigEndian);
+    let result = external_sort.sort(&format, &HeapSort, &[0u8, 1, 2, 3, 4, 5, 6, 7, 8][..], Vec::new());
+    match result {
+        Err(ExternalSortError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidData),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(temp_dir.files(), 0, "temporary files are removed on errors");
+
+    // count sort refuses too wide ranges of values
+    let format = Integers::<u64>::new(ByteOrder::BigEndian);
+    let mut input = Vec::new();
+    for value in &[u64::MAX, 0] {
+        format.write(&mut input, value).expect("writing to a vector doesn't fail");
+    }
+    let result = external_sort.sort(&format, &CountSort, input.as_slice(), Vec::new());
+    assert!(matches!(result, Err(ExternalSortError::Sort(_))));
+
+    let result = external_sort.sort_file(&Lines, &HeapSort, &temp_dir.path().join("missing"), &temp_dir.path().join("output"));
+    assert!(matches!(result, Err(ExternalSortError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound));
+}
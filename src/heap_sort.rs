@@ -3,11 +3,16 @@
 //! Notable that this is "in-place" algorithm with a quite effective time complexity.
 //! However, to reach this we need to maintain all the data in the [heap](https://en.wikipedia.org/wiki/Heap_(data_structure)) data structure.
 
+use std::cmp::Ordering;
+
+use crate::probe::{self, NoProbe, Probe};
+
 /// Heap sort implementation.
 ///
 /// We can classify heaps in to two different kinds:
 /// - min heap, where nodes are presented in a non-decreasing order;
 /// - max heap, where nodes are presented in a non-increasing order.
+///
 /// This implementation uses max heap to sort `src`.
 ///
 /// We could create a heap for this task, but as long as we only need to sort `src`, then we omit explicitly creating the data structure.
@@ -28,7 +33,8 @@
 /// 1. Build max heap.
 /// 2. Swap the first element, which is the largest in the heap, with the last.
 /// 3. Call max heapify starting from the first element, which violated the order. But heapify work on the different scope now - `src[..last_swapped]`.
-/// So the idea is that we put to the end biggest values, which were in the root of the heap, and maintain the order in the other "unsorted" part of the `src`.
+///    So the idea is that we put to the end biggest values, which were in the root of the heap, and maintain the order in the other "unsorted"
+///    part of the `src`.
 /// 4. Repeat until heap has only one element.
 ///
 /// # NOTE
@@ -37,28 +43,40 @@
 /// maintain the max heap order. Also, heapify should be called only in the parent, which violates the order, because otherwise you can skip a violated
 /// subtree.
 pub fn heap_sort<T: PartialOrd + Clone>(src: &mut [T]) {
+    heap_sort_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [heap_sort](fn.heap_sort.html), but elements are compared by `compare`.
+pub fn heap_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    heap_sort_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [heap_sort](fn.heap_sort.html), but elements are compared by keys extracted with `key`.
+pub fn heap_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    heap_sort_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn heap_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
     match src.len() {
         0 | 1 => {}
         2 => {
-            if src[0] > src[1] {
-                src.swap(0, 1)
+            if is_less(&src[1], &src[0]) {
+                probe::swap(src, 0, 1, probe)
+            }
+        }
+        _ => {
+            build_max_heap(src, is_less, probe);
+            let mut heap_size = src.len();
+            for node_index in (1..heap_size).rev() {
+                probe::swap(src, 0, node_index, probe);
+                heap_size -= 1;
+                max_heapify(&mut src[..heap_size], 0, is_less, probe);
             }
         }
-        _ => heap_sort_impl(src),
-    }
-}
-
-fn heap_sort_impl<T: PartialOrd + Clone>(src: &mut [T]) {
-    build_max_heap(src);
-    let mut heap_size = src.len();
-    for node_index in (1..heap_size).rev() {
-        src.swap(0, node_index);
-        heap_size -= 1;
-        max_heapify(&mut src[..heap_size], 0);
     }
 }
 
-fn build_max_heap<T: PartialOrd + Clone>(src: &mut [T]) {
+pub(crate) fn build_max_heap<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
     let last_leaf_index = src.len() - 1;
     let last_leaf_parent_index = match last_leaf_index % 2 {
         0 => last_leaf_index / 2 - 1,
@@ -66,12 +84,12 @@ fn build_max_heap<T: PartialOrd + Clone>(src: &mut [T]) {
         _ => unreachable!(),
     };
     for node_index in (0..=last_leaf_parent_index).rev() {
-        max_heapify(src, node_index);
+        max_heapify(src, node_index, is_less, probe);
     }
 }
 
 // Recursive version is very expensive and leads to stack overflow
-fn max_heapify<T: PartialOrd + Clone>(src: &mut [T], start_from: usize) {
+pub(crate) fn max_heapify<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], start_from: usize, is_less: &mut F, probe: &P) {
     let mut largest_index = start_from;
     loop {
         let parent_index = largest_index;
@@ -83,12 +101,13 @@ fn max_heapify<T: PartialOrd + Clone>(src: &mut [T], start_from: usize) {
             .iter()
             .filter_map(|&idx| src.get(idx))
             .enumerate()
-            .reduce(|tup1, tup2| if tup1.1 > tup2.1 { tup1 } else { tup2 })
+            .reduce(|tup1, tup2| if is_less(tup2.1, tup1.1) { tup1 } else { tup2 })
             .map(|(idx, _)| subtree[idx])
             .expect("iterator isn't empty");
 
         if parent_index != largest_index {
-            src.swap(parent_index, largest_index);
+            probe.sift(&src[parent_index], &src[largest_index]);
+            probe::swap(src, parent_index, largest_index, probe);
             continue;
         }
         return;
@@ -97,7 +116,8 @@ fn max_heapify<T: PartialOrd + Clone>(src: &mut [T], start_from: usize) {
 
 #[test]
 fn heap_sort_test() {
-    use crate::test_utils::test_sorting_algorithm;
+    use crate::test_utils::{test_sorting_algorithm, test_sorting_algorithm_by};
 
     assert!(test_sorting_algorithm(heap_sort).is_ok());
+    assert!(test_sorting_algorithm_by(heap_sort_by, heap_sort_by_key).is_ok());
 }
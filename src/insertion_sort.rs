@@ -1,15 +1,33 @@
 //! Insertion sort. Θ(n^2).
 //! Incremental algorithm which looks for a proper place in sorted area for the value from unsorted area.
 
+use std::cmp::Ordering;
+
+use crate::probe::{self, NoProbe, Probe};
+
 /// Insertion sort implementation.
 ///
 /// Finds for `src[cur]` value it's place in sorted area (which is [0; cur)
 /// by moving it to the left everytime there is a value bigger than it in the sorted area.
 pub fn insertion_sort<T: PartialOrd + Clone>(src: &mut [T]) {
+    insertion_sort_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [insertion_sort](fn.insertion_sort.html), but elements are compared by `compare`.
+pub fn insertion_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    insertion_sort_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [insertion_sort](fn.insertion_sort.html), but elements are compared by keys extracted with `key`.
+pub fn insertion_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    insertion_sort_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn insertion_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
     for cur in 1..src.len() {
         let mut i = cur;
-        while i > 0 && src[i] < src[i - 1] {
-            src.swap(i, i - 1);
+        while i > 0 && is_less(&src[i], &src[i - 1]) {
+            probe::swap(src, i, i - 1, probe);
             i -= 1;
         }
     }
@@ -71,9 +89,10 @@ fn insertion_sort_alternative<T: PartialOrd + Clone>(src: &mut [T]) {
 
 #[test]
 fn insertion_sort_test() {
-    use crate::test_utils::test_sorting_algorithm;
+    use crate::test_utils::{test_sorting_algorithm, test_sorting_algorithm_by};
 
     assert!(test_sorting_algorithm(insertion_sort_alternative).is_ok());
     assert!(test_sorting_algorithm(insertion_sort_explicit).is_ok());
     assert!(test_sorting_algorithm(insertion_sort).is_ok());
+    assert!(test_sorting_algorithm_by(insertion_sort_by, insertion_sort_by_key).is_ok());
 }
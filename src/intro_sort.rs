@@ -0,0 +1,117 @@
+//! Introsort. O(n*log n) in the worst case.
+//!
+//! Introspective sort is a hybrid of the crate's sorting algorithms, each of them doing what it does best:
+//! - [quick sort](../quick_sort/index.html) partitioning does the most of the work, because it's fast in the average case;
+//! - [heap sort](../heap_sort/index.html) takes over a subarray, when the depth of recursion exceeds *2 * log n*. Such depth means partitioning
+//!   is too unbalanced and quick sort is heading to its Θ(n^2) worst case, while heap sort is O(n*log n) in any case;
+//! - [insertion sort](../insertion_sort/index.html) finishes small subarrays, because it has little overhead and is fast on tiny inputs.
+//!
+//! So we get an "in-place" sort with a guaranteed O(n*log n) time, which is built entirely from the existing pieces.
+
+use std::cmp::Ordering;
+
+use crate::heap_sort::heap_sort_impl;
+use crate::insertion_sort::insertion_sort_impl;
+use crate::probe::{NoProbe, Probe};
+use crate::quick_sort::Partitioner;
+use crate::random::Rng;
+
+/// Subarrays of this length or shorter are sorted by insertion sort.
+const INSERTION_SORT_THRESHOLD: usize = 16;
+
+/// Introsort implementation.
+///
+/// Partitioning is done by `partitioner`, so it's the same as in [quick_sort](../quick_sort/fn.quick_sort.html). However, the worst case of
+/// the partitioner doesn't matter here: after *2 * log n* nested partitionings the subarray is sorted by heap sort.
+pub fn intro_sort<T: PartialOrd + Clone>(src: &mut [T], partitioner: Partitioner) {
+    intro_sort_with_limit(src, partitioner, &mut T::lt, &NoProbe)
+}
+
+/// Same as [intro_sort](fn.intro_sort.html), but elements are compared by `compare`.
+pub fn intro_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], partitioner: Partitioner, mut compare: F) {
+    intro_sort_with_limit(src, partitioner, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [intro_sort](fn.intro_sort.html), but elements are compared by keys extracted with `key`.
+pub fn intro_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], partitioner: Partitioner, mut key: F) {
+    intro_sort_with_limit(src, partitioner, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn intro_sort_with_limit<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], partitioner: Partitioner, is_less: &mut F, probe: &P) {
+    if src.len() <= 1 {
+        return;
+    }
+    let depth_limit = 2 * floor_log2(src.len());
+    intro_sort_impl(src, partitioner, &mut partitioner.rng(), is_less, probe, depth_limit);
+}
+
+fn intro_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(
+    src: &mut [T],
+    partitioner: Partitioner,
+    rng: &mut Rng,
+    is_less: &mut F,
+    probe: &P,
+    depth_limit: usize,
+) {
+    probe.enter();
+    if src.len() <= INSERTION_SORT_THRESHOLD {
+        insertion_sort_impl(src, is_less, probe);
+    } else if depth_limit == 0 {
+        heap_sort_impl(src, is_less, probe);
+    } else {
+        let (end_left, start_right) = partitioner.partition(src, rng, is_less, probe);
+        intro_sort_impl(&mut src[..end_left], partitioner, rng, is_less, probe, depth_limit - 1);
+        intro_sort_impl(&mut src[start_right..], partitioner, rng, is_less, probe, depth_limit - 1);
+    }
+    probe.leave();
+}
+
+fn floor_log2(num: usize) -> usize {
+    (usize::BITS - 1 - num.leading_zeros()) as usize
+}
+
+#[test]
+fn intro_sort_test() {
+    use crate::quick_sort::Scheme;
+    use crate::test_utils::get_test_vectors;
+
+    let partitioners = [
+        Partitioner::Lomuto,
+        Partitioner::Hoare,
+        Partitioner::ThreeWay,
+        Partitioner::Randomized {
+            scheme: Scheme::Lomuto,
+            seed: 3,
+        },
+    ];
+    for &partitioner in &partitioners {
+        for (input, sorted) in get_test_vectors().iter_mut() {
+            intro_sort(input, partitioner);
+            assert_eq!(input, sorted);
+        }
+
+        // Worst cases for Lomuto's and Hoare's partitioning. Without switching to heap sort it would take Θ(n^2) time.
+        let n = 50_000;
+        let mut src: Vec<u32> = (0..n).collect();
+        intro_sort(&mut src, partitioner);
+        assert!(src.iter().copied().eq(0..n));
+
+        let mut src: Vec<u32> = (0..n).rev().collect();
+        intro_sort(&mut src, partitioner);
+        assert!(src.iter().copied().eq(0..n));
+
+        let mut src = vec![7; n as usize];
+        intro_sort(&mut src, partitioner);
+        assert!(src.iter().all(|&v| v == 7));
+    }
+
+    assert!(crate::test_utils::test_sorting_algorithm_by(
+        |src, compare| intro_sort_by(src, Partitioner::Lomuto, compare),
+        |src, key| intro_sort_by_key(src, Partitioner::Hoare, key)
+    )
+    .is_ok());
+
+    assert_eq!(floor_log2(1), 0);
+    assert_eq!(floor_log2(16), 4);
+    assert_eq!(floor_log2(17), 4);
+}
@@ -0,0 +1,217 @@
+//! Counting inversions (CLRS Problem 2-4). Θ(n*log n).
+//!
+//! A pair of indices *i < j* is an *inversion*, if `src[i] > src[j]`. The number of inversions measures how far the sequence is from sorted:
+//! sorted one has none, reverse sorted one has all the *n(n-1)/2* pairs inverted. It's also the number of swaps
+//! [insertion sort](../insertion_sort/index.html) makes, because each swap of adjacent elements fixes exactly one inversion.
+//!
+//! Checking all the pairs takes Θ(n^2) time. Merge sort does it faster: inversions inside the halves are counted by recursive calls,
+//! and inversions between the halves are counted by `merge`. Whenever a right element is moved before the left elements, which remain
+//! in the buffer, it forms an inversion with each of them.
+//!
+//! The count of inversions between two rankings of the same items is [Kendall tau distance](https://en.wikipedia.org/wiki/Kendall_tau_distance),
+//! which shows how much the rankings disagree.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::merge_sort::{merge, merge_sort_by};
+use crate::probe::NoProbe;
+
+/// Error returned by [kendall_tau_distance](fn.kendall_tau_distance.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KendallTauError {
+    /// Rankings have different lengths.
+    LengthMismatch { left: usize, right: usize },
+    /// Rankings aren't permutations of the same distinct items.
+    NotPermutation,
+}
+
+impl fmt::Display for KendallTauError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KendallTauError::LengthMismatch { left, right } => write!(f, "rankings have different lengths: {} and {}", left, right),
+            KendallTauError::NotPermutation => write!(f, "rankings aren't permutations of the same distinct items"),
+        }
+    }
+}
+
+impl Error for KendallTauError {}
+
+/// Returns the number of inversions in `src`.
+///
+/// References to the elements are merge sorted, so `src` stays untouched and elements aren't cloned.
+/// Elements are expected to be totally ordered, otherwise (e.g. with `NaN`) the count is meaningless.
+pub fn count_inversions<T: PartialOrd>(src: &[T]) -> u64 {
+    let mut refs: Vec<&T> = src.iter().collect();
+    let mut buffer = Vec::with_capacity(refs.len().div_ceil(2));
+    sort_counting(&mut refs, &mut buffer, &mut |a, b| a < b)
+}
+
+/// Merge sorts `src` and returns the number of inversions in it.
+fn sort_counting<T, F: FnMut(&T, &T) -> bool>(src: &mut [T], buffer: &mut Vec<T>, is_less: &mut F) -> u64 {
+    if src.len() < 2 {
+        return 0;
+    }
+    let mid = src.len().div_ceil(2);
+    let left = sort_counting(&mut src[..mid], buffer, is_less);
+    let right = sort_counting(&mut src[mid..], buffer, is_less);
+    left + right + merge(src, mid, buffer, is_less, &NoProbe)
+}
+
+/// Returns up to `limit` inversions of `src` as `(i, j)` pairs of indices sorted in the lexicographical order.
+///
+/// If there are more than `limit` inversions, it's unspecified which of them are returned. The time is Θ(n*log n + limit),
+/// so listing a few inversions of a long input is cheap, even if there are Θ(n^2) of them.
+pub fn inverted_pairs<T: PartialOrd>(src: &[T], limit: usize) -> Vec<(usize, usize)> {
+    let mut indices: Vec<usize> = (0..src.len()).collect();
+    let mut buffer = Vec::with_capacity(indices.len().div_ceil(2));
+    let mut pairs = Vec::new();
+    collect_pairs(src, &mut indices, &mut buffer, limit, &mut pairs);
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Merge sorts `indices` of a subarray of `src` by their values and pushes inversions between them to `pairs` until there are `limit` ones.
+///
+/// Indices of the left half are less than indices of the right half. For each right element the left elements greater than it are
+/// a suffix of the sorted left half, and the suffix shrinks as the right elements grow, so all of them are found in a single pass.
+fn collect_pairs<T: PartialOrd>(src: &[T], indices: &mut [usize], buffer: &mut Vec<usize>, limit: usize, pairs: &mut Vec<(usize, usize)>) {
+    if indices.len() < 2 || pairs.len() >= limit {
+        return;
+    }
+    let mid = indices.len().div_ceil(2);
+    collect_pairs(src, &mut indices[..mid], buffer, limit, pairs);
+    collect_pairs(src, &mut indices[mid..], buffer, limit, pairs);
+    if pairs.len() >= limit {
+        // halves may be left unsorted, but nothing is collected after that
+        return;
+    }
+
+    let (left, right) = indices.split_at(mid);
+    let mut first_greater = 0;
+    for &j in right {
+        while first_greater < left.len() && src[left[first_greater]] <= src[j] {
+            first_greater += 1;
+        }
+        for &i in &left[first_greater..] {
+            if pairs.len() == limit {
+                return;
+            }
+            pairs.push((i, j));
+        }
+    }
+    merge(indices, mid, buffer, &mut |&a, &b| src[a] < src[b], &NoProbe);
+}
+
+/// Returns Kendall tau distance between two rankings, i.e. the number of pairs of items ranked in different order by `a` and `b`.
+///
+/// Each item of `a` is replaced by its position in `b`, and inversions of these positions are counted. So it's 0 for equal rankings
+/// and *n(n-1)/2* for reversed ones. Divide it by *n(n-1)/2* to get the normalized distance in `0.0..=1.0`.
+///
+/// Returns [KendallTauError](enum.KendallTauError.html), if `a` and `b` aren't permutations of the same distinct items.
+///
+/// ```
+/// use clrs_algos::inversions::kendall_tau_distance;
+///
+/// let ranking = ["rust", "go", "c", "zig"];
+/// assert_eq!(kendall_tau_distance(&ranking, &["rust", "c", "go", "zig"]), Ok(1));
+/// assert_eq!(kendall_tau_distance(&ranking, &["zig", "c", "go", "rust"]), Ok(6));
+/// ```
+pub fn kendall_tau_distance<T: Ord>(a: &[T], b: &[T]) -> Result<u64, KendallTauError> {
+    if a.len() != b.len() {
+        return Err(KendallTauError::LengthMismatch { left: a.len(), right: b.len() });
+    }
+    // positions of `b` sorted by their items, so the position of an item is found by binary search
+    let mut by_item: Vec<usize> = (0..b.len()).collect();
+    merge_sort_by(&mut by_item, |&i, &j| b[i].cmp(&b[j]));
+    if by_item.windows(2).any(|w| b[w[0]] == b[w[1]]) {
+        return Err(KendallTauError::NotPermutation);
+    }
+
+    let mut seen = vec![false; b.len()];
+    let mut positions = Vec::with_capacity(a.len());
+    for item in a {
+        let idx = by_item.binary_search_by(|&i| b[i].cmp(item)).map_err(|_| KendallTauError::NotPermutation)?;
+        let position = by_item[idx];
+        if seen[position] {
+            return Err(KendallTauError::NotPermutation);
+        }
+        seen[position] = true;
+        positions.push(position);
+    }
+    Ok(count_inversions(&positions))
+}
+
+/// Θ(n^2) reference: checks all the pairs.
+#[cfg(test)]
+fn inverted_pairs_naive<T: PartialOrd>(src: &[T]) -> Vec<(usize, usize)> {
+    (0..src.len())
+        .flat_map(|i| (i + 1..src.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| src[i] > src[j])
+        .collect()
+}
+
+#[test]
+fn count_inversions_test() {
+    use crate::sorter::{InsertionSort, Sorter};
+    use crate::test_utils::{get_random_vector, get_test_vectors};
+
+    assert_eq!(count_inversions::<i32>(&[]), 0);
+    assert_eq!(count_inversions(&[1]), 0);
+    // CLRS Problem 2-4: inversions of ⟨2, 3, 8, 6, 1⟩ are (1, 5), (2, 5), (3, 4), (3, 5), (4, 5) in 1-based indices
+    assert_eq!(count_inversions(&[2, 3, 8, 6, 1]), 5);
+    assert_eq!(inverted_pairs(&[2, 3, 8, 6, 1], usize::MAX), vec![(0, 4), (1, 4), (2, 3), (2, 4), (3, 4)]);
+    // equal elements aren't inversions
+    assert_eq!(count_inversions(&[1, 1, 1]), 0);
+    assert_eq!(count_inversions(&(0..1_000u32).rev().collect::<Vec<_>>()), 1_000 * 999 / 2);
+    assert_eq!(count_inversions(&[0.5, -1.0, 2.0, 0.0]), 3);
+
+    for (input, _) in get_test_vectors().iter() {
+        assert_eq!(count_inversions(input), inverted_pairs_naive(input).len() as u64, "{:?}", input);
+        assert_eq!(inverted_pairs(input, usize::MAX), inverted_pairs_naive(input), "{:?}", input);
+    }
+
+    // each swap of insertion sort fixes exactly one inversion
+    for &(len, max) in &[(10, 5), (100, 1_000), (1_000, 50), (1_001, u32::MAX)] {
+        let input = get_random_vector(len, max, len as u64);
+        let inversions = count_inversions(&input);
+        assert_eq!(inverted_pairs(&input, usize::MAX).len() as u64, inversions);
+        let stats = InsertionSort.sort_with_stats(&mut input.clone()).expect("insertion sort doesn't fail");
+        assert_eq!(stats.swaps, inversions);
+    }
+}
+
+#[test]
+fn inverted_pairs_limit_test() {
+    use crate::test_utils::get_random_vector;
+
+    let input = get_random_vector(300, 100, 18);
+    let all = inverted_pairs_naive(&input);
+    assert!(all.len() > 1_000);
+    for &limit in &[0, 1, 7, 1_000, all.len(), all.len() + 1] {
+        let pairs = inverted_pairs(&input, limit);
+        assert_eq!(pairs.len(), limit.min(all.len()));
+        assert!(pairs.windows(2).all(|w| w[0] < w[1]), "pairs are sorted and distinct");
+        assert!(pairs.iter().all(|&(i, j)| i < j && input[i] > input[j]));
+    }
+}
+
+#[test]
+fn kendall_tau_distance_test() {
+    assert_eq!(kendall_tau_distance::<u8>(&[], &[]), Ok(0));
+    assert_eq!(kendall_tau_distance(&[1, 2, 3, 4, 5], &[3, 4, 1, 2, 5]), Ok(4));
+    // the distance is symmetric
+    assert_eq!(kendall_tau_distance(&[3, 4, 1, 2, 5], &[1, 2, 3, 4, 5]), Ok(4));
+    let ranking: Vec<String> = (0..100).map(|i| format!("item {}", i)).collect();
+    let reversed: Vec<String> = ranking.iter().rev().cloned().collect();
+    assert_eq!(kendall_tau_distance(&ranking, &ranking), Ok(0));
+    assert_eq!(kendall_tau_distance(&ranking, &reversed), Ok(100 * 99 / 2));
+
+    assert_eq!(
+        kendall_tau_distance(&[1, 2], &[1, 2, 3]),
+        Err(KendallTauError::LengthMismatch { left: 2, right: 3 })
+    );
+    assert_eq!(kendall_tau_distance(&[1, 2, 3], &[1, 2, 4]), Err(KendallTauError::NotPermutation));
+    assert_eq!(kendall_tau_distance(&[1, 1, 2], &[1, 2, 3]), Err(KendallTauError::NotPermutation));
+    assert_eq!(kendall_tau_distance(&[1, 2, 3], &[1, 2, 2]), Err(KendallTauError::NotPermutation));
+}
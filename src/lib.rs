@@ -1,11 +1,23 @@
 //! The crate has some of algorithms and data structures from the "Introduction to Algorithms" book.
 
 pub mod bubble_sort;
+pub mod bucket_sort;
 pub mod count_sort;
+pub mod external_sort;
 pub mod heap_sort;
 pub mod insertion_sort;
+pub mod intro_sort;
+pub mod inversions;
 pub mod max_subarray;
 pub mod merge_sort;
+pub mod natural_merge_sort;
+pub mod parallel;
+pub mod probe;
 pub mod quick_sort;
 pub mod radix_sort;
+mod random;
+pub mod select;
+pub mod sorter;
 mod test_utils;
+#[cfg(feature = "trace")]
+pub mod trace;
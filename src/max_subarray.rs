@@ -17,8 +17,8 @@ pub fn find_max_sum_subarray_kadane<T: PartialOrd + Copy + Default + Add<Output
     let mut right = None;
 
     let mut cur_left = 0;
-    for i in 0..src.len() {
-        cur_sum = cur_sum + src[i];
+    for (i, &value) in src.iter().enumerate() {
+        cur_sum = cur_sum + value;
         if cur_sum >= max_sum {
             max_sum = cur_sum;
             right = Some(i);
@@ -87,8 +87,8 @@ fn find_max_sum_cross_subarray<T: Ord + Copy + Default + Add<Output = T>>(src: &
 
     cur_sum = T::default();
     let mut right_sum = T::default();
-    for j in mid..src.len() {
-        cur_sum = cur_sum + src[j];
+    for (j, &value) in src.iter().enumerate().skip(mid) {
+        cur_sum = cur_sum + value;
         if cur_sum >= right_sum {
             right_sum = cur_sum;
             right = Some(j);
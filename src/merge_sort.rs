@@ -1,62 +1,278 @@
 //! Merge sort. O(n*log n).
 //! Algorithm works using Divide & Conquer (& Combine) strategy.
 
+use std::cmp::Ordering;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use crate::insertion_sort::insertion_sort_impl;
+use crate::probe::{NoProbe, Probe};
+
 /// Merge sort
 ///
 /// Basically, this merge sort divides an input array into small subarrays until their sizes will be so small
 /// that finding solution for them will be incredibly easy (i.e. O(1).
 /// After the division we should "combine" sorted subarrays using an appropriate procedure (i.e. `merge`).
-pub fn merge_sort<T: PartialOrd + Clone + Default>(src: &mut [T]) {
-    match src.len() {
-        0 | 1 => {}
-        2 => {
-            if src[0] > src[1] {
-                src.swap(0, 1)
-            }
+///
+/// Elements are moved, not cloned, so any `T` can be sorted, even if it's neither `Clone` nor `Default` (`NonZeroU32`, file handles and so on).
+/// A single buffer for the half of the input is allocated once for the whole sort.
+///
+/// If the comparison panics, `src` is left with the same elements in some order: none of them is lost or duplicated.
+pub fn merge_sort<T: PartialOrd>(src: &mut [T]) {
+    merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut T::lt, &NoProbe)
+}
+
+/// Same as [merge_sort](fn.merge_sort.html), but elements are compared by `compare`.
+pub fn merge_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [merge_sort](fn.merge_sort.html), but elements are compared by keys extracted with `key`.
+pub fn merge_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+/// Subarrays of this length or shorter are sorted by insertion sort in [merge_sort](fn.merge_sort.html),
+/// i.e. two elements are just swapped if they're out of order, as in the textbook merge sort.
+pub(crate) const MERGE_SORT_THRESHOLD: usize = 2;
+
+/// Hybrid of merge sort and insertion sort (CLRS Problem 2-1).
+///
+/// Subarrays of `threshold` or fewer elements aren't divided further, but sorted by [insertion sort](../insertion_sort/index.html).
+/// Insertion sort of *n/k* subarrays of length *k* takes Θ(nk) time in the worst case, and merging them takes Θ(n*log(n/k)) time.
+/// Asymptotically it's still Θ(n*log n) for *k* = O(log n), but insertion sort has smaller constant factors and no recursion overhead,
+/// so a small `threshold` makes the sort faster in practice. The best value depends on the element type and the machine,
+/// [tune_hybrid_threshold](fn.tune_hybrid_threshold.html) finds it by measurements.
+///
+/// `threshold` of 2 or less makes it the plain [merge_sort](fn.merge_sort.html), and `threshold` not less than the input length
+/// makes it insertion sort. Like merge sort, it's stable and panic-safe.
+pub fn merge_sort_hybrid<T: PartialOrd>(src: &mut [T], threshold: usize) {
+    merge_sort_impl(src, threshold, &mut T::lt, &NoProbe)
+}
+
+/// Same as [merge_sort_hybrid](fn.merge_sort_hybrid.html), but elements are compared by `compare`.
+pub fn merge_sort_hybrid_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], threshold: usize, mut compare: F) {
+    merge_sort_impl(src, threshold, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [merge_sort_hybrid](fn.merge_sort_hybrid.html), but elements are compared by keys extracted with `key`.
+pub fn merge_sort_hybrid_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], threshold: usize, mut key: F) {
+    merge_sort_impl(src, threshold, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+/// Returns the threshold from `thresholds`, with which [merge_sort_hybrid](fn.merge_sort_hybrid.html) is the fastest on inputs
+/// made by `generate`, or `None` if `thresholds` is empty.
+///
+/// `rounds` inputs are generated once, and each threshold sorts copies of the same inputs, so thresholds are compared on equal terms.
+/// Timings are noisy: inputs should have at least several thousands elements, and the result is a hint valid for the machine,
+/// the element type and the comparison cost it was measured with. Run it in release mode, in debug mode insertion sort
+/// is penalized by bounds checks.
+///
+/// ```
+/// use clrs_algos::merge_sort::{merge_sort_hybrid, tune_hybrid_threshold};
+///
+/// let mut seed = 42u64;
+/// let mut generate = || {
+///     (0..10_000)
+///         .map(|_| {
+///             seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+///             seed >> 33
+///         })
+///         .collect::<Vec<u64>>()
+/// };
+/// let threshold = tune_hybrid_threshold(&[4, 8, 16, 32, 64], 3, &mut generate).unwrap();
+/// assert!([4, 8, 16, 32, 64].contains(&threshold));
+///
+/// let mut src = generate();
+/// merge_sort_hybrid(&mut src, threshold);
+/// assert!(src.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+pub fn tune_hybrid_threshold<T: PartialOrd + Clone, G: FnMut() -> Vec<T>>(thresholds: &[usize], rounds: usize, mut generate: G) -> Option<usize> {
+    let inputs: Vec<Vec<T>> = (0..rounds).map(|_| generate()).collect();
+    thresholds.iter().copied().min_by_key(|&threshold| {
+        inputs
+            .iter()
+            .map(|input| {
+                let mut src = input.clone();
+                let start = Instant::now();
+                merge_sort_hybrid(&mut src, threshold);
+                start.elapsed()
+            })
+            .sum::<Duration>()
+    })
+}
+
+pub(crate) fn merge_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], threshold: usize, is_less: &mut F, probe: &P) {
+    // the left half, which is moved to the buffer by `merge`, has at most ⌈n/2⌉ elements.
+    // The buffer's length stays 0, so elements in it are never dropped by it.
+    let mut buffer = Vec::with_capacity(src.len().div_ceil(2));
+    merge_sort_recursive(src, threshold, &mut buffer, is_less, probe);
+}
+
+fn merge_sort_recursive<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], threshold: usize, buffer: &mut Vec<T>, is_less: &mut F, probe: &P) {
+    probe.enter();
+    if src.len() <= threshold.max(1) {
+        insertion_sort_impl(src, is_less, probe);
+    } else {
+        // Divide: middle element index is q-1
+        let q = src.len().div_ceil(2);
+        // Conquer
+        merge_sort_recursive(&mut src[..q], threshold, buffer, is_less, probe);
+        merge_sort_recursive(&mut src[q..], threshold, buffer, is_less, probe);
+        // Combine
+        merge(src, q, buffer, is_less, probe);
+    }
+    probe.leave();
+}
+
+/// Merges sorted `src[..mid]` and `src[mid..]`.
+///
+/// The left run is moved to the spare capacity of `buffer` (bitwise, like `mem::take` does, but without leaving a default value).
+/// So `src[..mid]` becomes a "hole", and elements are moved to the hole from the beginning of the buffer or of the right run.
+/// The hole always ends where the rest of the right run starts, and it's as long as the rest of the left run, so moving elements
+/// never overwrites elements not moved yet.
+///
+/// If `is_less` panics, the rest of the left run is moved back to the hole by `MergeHole` drop, so each element is in `src` exactly once.
+///
+/// Returns the number of inversions between the runs, i.e. pairs of a left element and a less right element (CLRS Problem 2-4).
+/// When a right element is moved, it's less than all the left elements remaining in the buffer.
+pub(crate) fn merge<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], mid: usize, buffer: &mut Vec<T>, is_less: &mut F, probe: &P) -> u64 {
+    assert!(buffer.is_empty() && buffer.capacity() >= mid, "buffer has space for the left run");
+    probe.merge(src, mid);
+    let len = src.len();
+    let src = src.as_mut_ptr();
+    let buf = buffer.as_mut_ptr();
+    // SAFETY: `buf` has space for `mid` elements and doesn't overlap with `src`. Each element is either in `src[..hole.dest]`,
+    // in `buf[hole.start..hole.end]` or in `src[right..]`, which are disjoint, and the hole drop moves the buffered ones back.
+    unsafe {
+        ptr::copy_nonoverlapping(src, buf, mid);
+        let mut hole = MergeHole {
+            buf,
+            start: 0,
+            end: mid,
+            dest: src,
+        };
+        for k in 0..mid {
+            probe.write(&*buf.add(k));
+        }
+
+        let mut right = mid;
+        let mut inversions = 0;
+        while hole.start < hole.end && right < len {
+            // the right element is taken only if it's less, so equal elements keep their order, i.e. the sort is stable
+            let smallest = if is_less(&*src.add(right), &*buf.add(hole.start)) {
+                inversions += (hole.end - hole.start) as u64;
+                right += 1;
+                src.add(right - 1)
+            } else {
+                hole.start += 1;
+                buf.add(hole.start - 1)
+            };
+            ptr::copy_nonoverlapping(smallest, hole.dest, 1);
+            // the hole is moved before the probe is called, so a panicking probe doesn't break `dest + (end - start) == right`
+            hole.dest = hole.dest.add(1);
+            probe.write(&*hole.dest.sub(1));
+        }
+        // the rest of the right run is already in its place
+        while hole.start < hole.end {
+            ptr::copy_nonoverlapping(buf.add(hole.start), hole.dest, 1);
+            hole.start += 1;
+            hole.dest = hole.dest.add(1);
+            probe.write(&*hole.dest.sub(1));
         }
-        _ => merge_sort_impl(src),
+        inversions
     }
 }
 
-fn merge_sort_impl<T: PartialOrd + Clone + Default>(src: &mut [T]) {
-    // Divide: middle element index is q-1
-    let q = (src.len() + 1) / 2;
-    // Conquer
-    merge_sort(&mut src[..q]);
-    merge_sort(&mut src[q..]);
-    // Combine
-    merge(src, q);
+/// Elements of the left run `buf[start..end]`, which are moved out of the input, and the position `dest`, where they are to be moved.
+struct MergeHole<T> {
+    buf: *mut T,
+    start: usize,
+    end: usize,
+    dest: *mut T,
 }
 
-fn merge<T: PartialOrd + Clone + Default>(src: &mut [T], mid: usize) {
-    let mut tmp = vec![T::default(); src.len()];
+impl<T> Drop for MergeHole<T> {
+    fn drop(&mut self) {
+        // SAFETY: see `merge`, `dest` is followed by exactly `end - start` moved out elements.
+        unsafe { ptr::copy_nonoverlapping(self.buf.add(self.start), self.dest, self.end - self.start) }
+    }
+}
+
+/// Bottom-up merge sort, which doesn't recurse.
+///
+/// Instead of dividing the input from the top, sorted runs are built from the bottom: each element is a sorted run of width 1,
+/// then pairs of adjacent runs are merged into runs of width 2, 4 and so on, until the run covers the whole input.
+///
+/// A single buffer as long as the input is allocated once. Each pass merges runs from one of `src` and the buffer into the other one,
+/// so they swap their roles ("ping-pong") and elements are never moved back between passes. Elements are moved by swapping,
+/// `Clone` is needed only to fill the buffer.
+///
+/// It's stable: of the equal elements the one from the left run is taken first.
+pub fn merge_sort_bottom_up<T: PartialOrd + Clone>(src: &mut [T]) {
+    merge_sort_bottom_up_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [merge_sort_bottom_up](fn.merge_sort_bottom_up.html), but elements are compared by `compare`.
+pub fn merge_sort_bottom_up_by<T: Clone, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    merge_sort_bottom_up_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [merge_sort_bottom_up](fn.merge_sort_bottom_up.html), but elements are compared by keys extracted with `key`.
+pub fn merge_sort_bottom_up_by_key<T: Clone, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    merge_sort_bottom_up_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn merge_sort_bottom_up_impl<T: Clone, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
+    let len = src.len();
+    if len <= 1 {
+        return;
+    }
+    src.iter().for_each(|value| probe.cloned(value));
+    let mut buffer = src.to_vec();
+    // whether the sorted runs of the current width are in `buffer` or in `src`
+    let mut runs_in_buffer = false;
+    let mut width = 1;
+    while width < len {
+        let (from, to) = if runs_in_buffer {
+            (&mut buffer[..], &mut src[..])
+        } else {
+            (&mut src[..], &mut buffer[..])
+        };
+        for start in (0..len).step_by(2 * width) {
+            let mid = (start + width).min(len);
+            let end = (start + 2 * width).min(len);
+            merge_into(&mut from[start..end], &mut to[start..end], mid - start, is_less, probe);
+        }
+        runs_in_buffer = !runs_in_buffer;
+        width *= 2;
+    }
+    if runs_in_buffer {
+        src.swap_with_slice(&mut buffer);
+        src.iter().for_each(|value| probe.write(value));
+    }
+}
 
+/// Merges sorted `from[..mid]` and `from[mid..]` into `to`. Elements are swapped into `to`, so `from` is left with the previous elements of `to`.
+fn merge_into<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(from: &mut [T], to: &mut [T], mid: usize, is_less: &mut F, probe: &P) {
+    probe.merge(from, mid);
     let mut i = 0;
     let mut j = mid;
-    let mut tmp_idx = 0;
-    while i < mid || j < src.len() {
-        let smallest_value_idx = if i == mid {
-            j += 1;
-            j - 1
-        } else if j == src.len() {
-            i += 1;
-            i - 1
-        } else if src[i] <= src[j] {
+    for slot in to.iter_mut() {
+        let smallest_value_idx = if j == from.len() || (i < mid && !is_less(&from[j], &from[i])) {
             i += 1;
             i - 1
         } else {
             j += 1;
             j - 1
         };
-        tmp[tmp_idx] = std::mem::take(&mut src[smallest_value_idx]);
-        tmp_idx += 1;
+        std::mem::swap(slot, &mut from[smallest_value_idx]);
+        probe.write(slot);
     }
-
-    src.clone_from_slice(&tmp);
 }
 
 /// A closer to CLRS book implementation of merge procedure.
-#[allow(unused)]
+#[allow(unused, clippy::needless_range_loop)]
 fn merge_clrs<T: PartialOrd + Clone + Default>(src: &mut [T], mid: usize) {
     let mut left = vec![T::default(); src[..mid].len()];
     let mut right = vec![T::default(); src[mid..].len()];
@@ -69,10 +285,7 @@ fn merge_clrs<T: PartialOrd + Clone + Default>(src: &mut [T], mid: usize) {
         if i == left.len() {
             src[k] = std::mem::take(&mut right[j]);
             j += 1;
-        } else if j == right.len() {
-            src[k] = std::mem::take(&mut left[i]);
-            i += 1;
-        } else if left[i] <= right[j] {
+        } else if j == right.len() || left[i] <= right[j] {
             src[k] = std::mem::take(&mut left[i]);
             i += 1;
         } else {
@@ -84,7 +297,161 @@ fn merge_clrs<T: PartialOrd + Clone + Default>(src: &mut [T], mid: usize) {
 
 #[test]
 fn merge_sort_test() {
-    use crate::test_utils::test_sorting_algorithm;
+    use crate::test_utils::{test_sorting_algorithm, test_sorting_algorithm_by};
 
     assert!(test_sorting_algorithm(merge_sort).is_ok());
+    assert!(test_sorting_algorithm_by(merge_sort_by, merge_sort_by_key).is_ok());
+}
+
+#[test]
+fn merge_sort_hybrid_test() {
+    use crate::probe::SortStats;
+    use crate::sorter::{InsertionSort, MergeSort, MergeSortHybrid, Sorter};
+    use crate::test_utils::{get_random_vector, get_records, is_sorted_stably, test_sorting_algorithm, test_sorting_algorithm_by};
+
+    assert!(test_sorting_algorithm(|src| merge_sort_hybrid(src, 4)).is_ok());
+    assert!(test_sorting_algorithm_by(
+        |src, compare| merge_sort_hybrid_by(src, 4, compare),
+        |src, key| merge_sort_hybrid_by_key(src, 4, key)
+    )
+    .is_ok());
+
+    let input = get_random_vector(1_000, 100, 16);
+    let mut expected = input.clone();
+    merge_sort(&mut expected);
+    for &threshold in &[0, 1, 2, 3, 7, 16, 64, 999, 1_000, usize::MAX] {
+        let mut src = input.clone();
+        merge_sort_hybrid(&mut src, threshold);
+        assert_eq!(src, expected, "threshold {}", threshold);
+    }
+
+    // the extreme thresholds give the plain merge sort and the insertion sort, operation by operation
+    let hybrid = MergeSortHybrid(2).sort_with_stats(&mut input.clone()).expect("merge sort doesn't fail");
+    assert_eq!(hybrid, MergeSort.sort_with_stats(&mut input.clone()).expect("merge sort doesn't fail"));
+    let hybrid = MergeSortHybrid(1_000).sort_with_stats(&mut input.clone()).expect("merge sort doesn't fail");
+    let insertion = InsertionSort.sort_with_stats(&mut input.clone()).expect("insertion sort doesn't fail");
+    // the only recursive call is the outermost one
+    assert_eq!(hybrid, SortStats { max_depth: 1, ..insertion });
+    // in between the recursion is shallower: 1000 / 2^6 < 16
+    let hybrid = MergeSortHybrid(16).sort_with_stats(&mut input.clone()).expect("merge sort doesn't fail");
+    assert_eq!(hybrid.max_depth, 7);
+
+    // stability: records with equal keys keep the order of their indices
+    let mut records = get_records(get_random_vector(1_001, 10, 17));
+    merge_sort_hybrid_by_key(&mut records, 32, |&(key, _)| key);
+    assert!(is_sorted_stably(&records));
+
+    assert_eq!(tune_hybrid_threshold(&[], 1, || vec![1, 2, 3]), None);
+    assert_eq!(tune_hybrid_threshold(&[8], 1, || vec![3, 2, 1]), Some(8));
+}
+
+#[test]
+fn merge_sort_bottom_up_test() {
+    use crate::test_utils::{get_random_vector, get_records, get_test_vectors, is_sorted_stably, test_sorting_algorithm, test_sorting_algorithm_by};
+
+    assert!(test_sorting_algorithm(merge_sort_bottom_up).is_ok());
+    assert!(test_sorting_algorithm_by(merge_sort_bottom_up_by, merge_sort_bottom_up_by_key).is_ok());
+
+    for (input, _) in get_test_vectors().iter() {
+        let mut expected = input.clone();
+        merge_sort(&mut expected);
+        let mut src = input.clone();
+        merge_sort_bottom_up(&mut src);
+        assert_eq!(src, expected);
+    }
+
+    // lengths, which aren't powers of two, leave the last run shorter, and the number of passes can be odd or even
+    for &len in &[3, 5, 1_000, 1 << 12, (1 << 12) + 1] {
+        let input = get_random_vector(len, 100, len as u64);
+        let mut expected = input.clone();
+        merge_sort(&mut expected);
+        let mut src = input.clone();
+        merge_sort_bottom_up(&mut src);
+        assert_eq!(src, expected);
+    }
+
+    // stability: records with equal keys keep the order of their indices
+    let mut records = get_records(get_random_vector(1_001, 10, 4));
+    merge_sort_bottom_up_by_key(&mut records, |&(key, _)| key);
+    assert!(is_sorted_stably(&records));
+}
+
+#[test]
+fn merge_sort_panic_safety_test() {
+    use std::num::NonZeroU32;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    use crate::test_utils::{get_random_vector, Droppable};
+
+    let mut src: Vec<NonZeroU32> = (1..=100).rev().filter_map(NonZeroU32::new).collect();
+    merge_sort(&mut src);
+    assert!(src.iter().map(|value| value.get()).eq(1..=100));
+
+    let keys = get_random_vector(100, 1_000, 6);
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort_unstable();
+    for &limit in &[0, 1, 50, 300, 1_000] {
+        let drops = AtomicUsize::new(0);
+        let mut src: Vec<Droppable> = keys.iter().map(|&key| Droppable { key, drops: &drops }).collect();
+        let mut comparisons = 0;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            merge_sort_by(&mut src, |a, b| {
+                comparisons += 1;
+                assert!(comparisons <= limit, "comparator failed");
+                a.key.cmp(&b.key)
+            })
+        }));
+        // the sort needs more than 300 comparisons, but less than 1000
+        assert_eq!(result.is_err(), limit <= 300);
+        // elements are neither dropped, nor duplicated
+        assert_eq!(drops.load(Relaxed), 0);
+        let mut src_keys: Vec<u32> = src.iter().map(|value| value.key).collect();
+        src_keys.sort_unstable();
+        assert_eq!(src_keys, sorted_keys);
+        drop(src);
+        assert_eq!(drops.load(Relaxed), keys.len());
+    }
+}
+
+#[test]
+fn merge_sort_probe_panic_safety_test() {
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::test_utils::get_random_vector;
+
+    // panics on the `limit`-th write, which may happen in the middle of a merge
+    struct PanickingProbe {
+        writes: Cell<usize>,
+        limit: usize,
+    }
+
+    impl<T> Probe<T> for PanickingProbe {
+        fn write(&self, _dst: &T) {
+            self.writes.set(self.writes.get() + 1);
+            assert!(self.writes.get() < self.limit, "probe failed");
+        }
+    }
+
+    let keys = get_random_vector(20, 10, 7);
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort_unstable();
+    let mut src = keys.clone();
+    let all_writes = PanickingProbe {
+        writes: Cell::new(0),
+        limit: usize::MAX,
+    };
+    merge_sort_impl(&mut src, MERGE_SORT_THRESHOLD, &mut u32::lt, &all_writes);
+    assert_eq!(src, sorted_keys);
+
+    for limit in 1..=all_writes.writes.get() {
+        let mut src = keys.clone();
+        let probe = PanickingProbe { writes: Cell::new(0), limit };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| merge_sort_impl(&mut src, MERGE_SORT_THRESHOLD, &mut u32::lt, &probe)));
+        assert!(result.is_err());
+        // elements are neither lost, nor duplicated
+        src.sort_unstable();
+        assert_eq!(src, sorted_keys, "probe panicked on the write {}", limit);
+    }
 }
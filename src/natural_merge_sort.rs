@@ -0,0 +1,224 @@
+//! Natural merge sort. O(n) for sorted input, O(n*log n) in the worst case.
+//!
+//! [Merge sort](../merge_sort/index.html) divides the input in halves regardless of the data, so it does Θ(n*log n) work even if the input
+//! is already sorted. But real data is often partially sorted: logs with some records appended, concatenated sorted shards and so on.
+//! Natural merge sort uses the order, which is already there. It scans the input for *runs*, i.e. maximal sorted subarrays, and merges them.
+//! Sorted input is a single run, so it's recognized in *n - 1* comparisons and nothing is merged.
+//!
+//! The details are borrowed from Tim Peters' TimSort:
+//! - strictly descending runs are reversed in place, so reverse sorted input takes O(n) time as well. Descending runs with equal elements
+//!   aren't reversed, because that would break the stability;
+//! - short runs are extended to the minimum run length by [insertion sort](../insertion_sort/index.html), because merging many tiny runs
+//!   costs more than insertion sort of them;
+//! - runs are pushed to a stack, and the top runs are merged while their lengths violate invariants, which are similar to the Fibonacci numbers
+//!   definition. So the stack has O(log n) runs, and each run is merged with a run of comparable length, as in merge sort.
+
+use std::cmp::Ordering;
+
+use crate::insertion_sort::insertion_sort_impl;
+use crate::merge_sort::merge;
+use crate::probe::{self, NoProbe, Probe};
+
+/// Inputs shorter than this are sorted by insertion sort, longer ones are divided into runs of at least `MIN_MERGE / 2` elements.
+const MIN_MERGE: usize = 64;
+
+/// Natural merge sort implementation.
+///
+/// Like [merge_sort](../merge_sort/fn.merge_sort.html), it's stable, moves elements instead of cloning them and is panic-safe.
+pub fn natural_merge_sort<T: PartialOrd>(src: &mut [T]) {
+    natural_merge_sort_impl(src, &mut T::lt, &NoProbe)
+}
+
+/// Same as [natural_merge_sort](fn.natural_merge_sort.html), but elements are compared by `compare`.
+pub fn natural_merge_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], mut compare: F) {
+    natural_merge_sort_impl(src, &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [natural_merge_sort](fn.natural_merge_sort.html), but elements are compared by keys extracted with `key`.
+pub fn natural_merge_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], mut key: F) {
+    natural_merge_sort_impl(src, &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+/// Sorted subarray `src[start..start + len]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Run {
+    start: usize,
+    len: usize,
+}
+
+pub(crate) fn natural_merge_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) {
+    let len = src.len();
+    let min_run = min_run_length(len);
+    // the left run of a merge can be almost as long as the input
+    let mut buffer = Vec::with_capacity(len);
+    let mut runs: Vec<Run> = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let mut run_len = find_run(&mut src[start..], is_less, probe);
+        if run_len < min_run {
+            let end = (start + min_run).min(len);
+            // the run is already sorted, so insertion sort only inserts the elements after it
+            insertion_sort_impl(&mut src[start..end], is_less, probe);
+            run_len = end - start;
+        }
+        runs.push(Run { start, len: run_len });
+        start += run_len;
+
+        while let Some(idx) = collapse(&runs, len) {
+            let (left, right) = (runs[idx], runs[idx + 1]);
+            merge(&mut src[left.start..right.start + right.len], left.len, &mut buffer, is_less, probe);
+            runs[idx].len += right.len;
+            runs.remove(idx + 1);
+        }
+    }
+}
+
+/// Returns the length of the run at the beginning of `src`. Strictly descending run is reversed, so it becomes ascending.
+fn find_run<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) -> usize {
+    if src.len() < 2 {
+        return src.len();
+    }
+    let mut end = 2;
+    if is_less(&src[1], &src[0]) {
+        while end < src.len() && is_less(&src[end], &src[end - 1]) {
+            end += 1;
+        }
+        for idx in 0..end / 2 {
+            probe::swap(src, idx, end - 1 - idx, probe);
+        }
+    } else {
+        while end < src.len() && !is_less(&src[end], &src[end - 1]) {
+            end += 1;
+        }
+    }
+    end
+}
+
+/// Returns the minimum run length for the input of length `len`.
+///
+/// Short inputs are sorted by insertion sort as a whole. For others it's the 6 most significant bits of `len` plus one, if any of the other
+/// bits is set. So `len / min_run` is a power of two or slightly less than it, and the runs are merged in a balanced way.
+fn min_run_length(mut len: usize) -> usize {
+    let mut low_bit_set = 0;
+    while len >= MIN_MERGE {
+        low_bit_set |= len & 1;
+        len >>= 1;
+    }
+    len + low_bit_set
+}
+
+/// Returns the index of the run to be merged with the next one, if lengths of the top runs violate the invariants of the stack.
+///
+/// For the top runs `..., W, Z, Y, X` the invariants are `Y > X`, `Z > Y + X` and `W > Z + Y` (the last one isn't in the original TimSort,
+/// without it the invariants can be violated deeper in the stack). `Y` is merged with the shorter of `X` and `Z`.
+/// When the top run ends at `len`, i.e. there are no more runs, all the runs are merged.
+fn collapse(runs: &[Run], len: usize) -> Option<usize> {
+    let n = runs.len();
+    if n < 2 {
+        return None;
+    }
+    let run_len = |from_top: usize| runs[n - 1 - from_top].len;
+    let is_last = runs[n - 1].start + runs[n - 1].len == len;
+    let violated = run_len(1) <= run_len(0) || (n >= 3 && run_len(2) <= run_len(1) + run_len(0)) || (n >= 4 && run_len(3) <= run_len(2) + run_len(1));
+    if !is_last && !violated {
+        None
+    } else if n >= 3 && run_len(2) < run_len(0) {
+        Some(n - 3)
+    } else {
+        Some(n - 2)
+    }
+}
+
+#[test]
+fn natural_merge_sort_test() {
+    use crate::merge_sort::merge_sort;
+    use crate::test_utils::{get_random_vector, get_records, is_sorted_stably, test_sorting_algorithm, test_sorting_algorithm_by};
+
+    assert!(test_sorting_algorithm(natural_merge_sort).is_ok());
+    assert!(test_sorting_algorithm_by(natural_merge_sort_by, natural_merge_sort_by_key).is_ok());
+
+    let n = 10_000;
+    let random = get_random_vector(n, u32::MAX, 12);
+    // appended log: sorted records with some new ones at the end
+    let mut appended: Vec<u32> = (0..n as u32 - 100).map(|i| i * 2).collect();
+    appended.extend(get_random_vector(100, 2 * n as u32, 13));
+    // merged shards: sorted chunks of random values
+    let mut shards = random.clone();
+    shards.chunks_mut(n / 7).for_each(|chunk| chunk.sort_unstable());
+    // sawtooth of descending runs with duplicates
+    let sawtooth: Vec<u32> = (0..n as u32).map(|i| (1_000 - i % 1_000) / 3).collect();
+    for input in [random, appended, shards, sawtooth].iter() {
+        for &len in &[n, 65, 64, 63, 1_000] {
+            let mut src = input[..len].to_vec();
+            let mut expected = src.clone();
+            merge_sort(&mut expected);
+            natural_merge_sort(&mut src);
+            assert_eq!(src, expected);
+        }
+    }
+
+    // stability: records with equal keys keep the order of their indices, including descending runs with equal keys
+    let keys = (0..2_000).rev().map(|i| i / 3).chain(get_random_vector(2_000, 10, 14));
+    let mut records = get_records(keys);
+    natural_merge_sort_by_key(&mut records, |&(key, _)| key);
+    assert!(is_sorted_stably(&records));
+}
+
+#[test]
+fn natural_merge_sort_adaptivity_test() {
+    use crate::sorter::{MergeSort, NaturalMergeSort, Sorter};
+    use crate::test_utils::get_random_vector;
+
+    let n = 10_000u64;
+    // sorted and strictly reverse sorted inputs are single runs
+    let mut src: Vec<u64> = (0..n).collect();
+    let stats = NaturalMergeSort.sort_with_stats(&mut src).expect("natural merge sort doesn't fail");
+    assert!(src.iter().copied().eq(0..n));
+    assert_eq!((stats.comparisons, stats.swaps, stats.moves), (n - 1, 0, 0));
+    let merge_sort_stats = MergeSort.sort_with_stats(&mut src).expect("merge sort doesn't fail");
+    assert!(merge_sort_stats.comparisons > 5 * n);
+
+    let mut src: Vec<u64> = (0..n).rev().collect();
+    let stats = NaturalMergeSort.sort_with_stats(&mut src).expect("natural merge sort doesn't fail");
+    assert!(src.iter().copied().eq(0..n));
+    assert_eq!((stats.comparisons, stats.swaps, stats.moves), (n - 1, n / 2, 0));
+
+    // a few sorted shards are merged in linear time, merge sort does the full work
+    let mut src: Vec<u64> = get_random_vector(n as usize, 1_000_000, 15).into_iter().map(u64::from).collect();
+    src.chunks_mut(n as usize / 4).for_each(|chunk| chunk.sort_unstable());
+    let mut merge_sort_src = src.clone();
+    let stats = NaturalMergeSort.sort_with_stats(&mut src).expect("natural merge sort doesn't fail");
+    let merge_sort_stats = MergeSort.sort_with_stats(&mut merge_sort_src).expect("merge sort doesn't fail");
+    assert_eq!(src, merge_sort_src);
+    assert!(stats.comparisons < 3 * n, "{:?}", stats);
+    assert!(merge_sort_stats.comparisons > 2 * stats.comparisons, "{:?}", merge_sort_stats);
+}
+
+#[test]
+fn runs_test() {
+    assert_eq!(min_run_length(0), 0);
+    assert_eq!(min_run_length(63), 63);
+    assert_eq!(min_run_length(64), 32);
+    assert_eq!(min_run_length(65), 33);
+    assert_eq!(min_run_length(1 << 20), 32);
+    assert_eq!(min_run_length((1 << 20) + 1), 33);
+    assert!((64..100_000).all(|len| (32..=64).contains(&min_run_length(len))));
+
+    let mut src = [5, 4, 4, 3, 7];
+    // not strictly descending run stops at equal elements
+    assert_eq!(find_run(&mut src, &mut i32::lt, &NoProbe), 2);
+    assert_eq!(src, [4, 5, 4, 3, 7]);
+    assert_eq!(find_run(&mut src[2..], &mut i32::lt, &NoProbe), 2);
+    assert_eq!(src, [4, 5, 3, 4, 7]);
+    assert_eq!(find_run(&mut src[1..], &mut i32::lt, &NoProbe), 2);
+    assert_eq!(find_run(&mut src[4..], &mut i32::lt, &NoProbe), 1);
+
+    let run = |start, len| Run { start, len };
+    assert_eq!(collapse(&[run(0, 100)], 1_000), None);
+    assert_eq!(collapse(&[run(0, 100), run(100, 50)], 1_000), None);
+    assert_eq!(collapse(&[run(0, 100), run(100, 50), run(150, 50)], 1_000), Some(1));
+    assert_eq!(collapse(&[run(0, 100), run(100, 60), run(160, 50)], 1_000), Some(1));
+    assert_eq!(collapse(&[run(0, 40), run(40, 50), run(90, 60)], 1_000), Some(0));
+    // the last run forces merging
+    assert_eq!(collapse(&[run(0, 100), run(100, 50)], 150), Some(0));
+}
@@ -0,0 +1,435 @@
+//! Parallel sorting with scoped threads (CLRS chapter 27).
+//!
+//! Divide & Conquer algorithms divide the input into independent subarrays, so the recursive calls can run on separate threads.
+//! Threads are spawned with [std::thread::scope](https://doc.rust-lang.org/std/thread/fn.scope.html), so they can borrow disjoint parts of the input
+//! and are joined before the sort returns. Spawning a thread is much more expensive than sorting a short subarray, so the parallelism
+//! is bounded by [Parallelism](struct.Parallelism.html): the number of threads working at the same time and the minimum length of a subarray
+//! handed to a thread. Below those limits the sequential algorithms are used.
+//!
+//! [par_merge_sort](fn.par_merge_sort.html) sorts the halves in parallel and merges them with P-MERGE, which is parallel as well.
+//! Otherwise the merge of the whole input would take Θ(n) time on a single thread, and the sort couldn't be more than Θ(log n) times faster
+//! than the sequential one.
+//!
+//! [par_quick_sort](fn.par_quick_sort.html) partitions the input and sorts the areas in parallel. It works in place, but the partitioning
+//! isn't parallel, and the areas can be unbalanced.
+
+use std::cmp::Ordering;
+use std::mem::MaybeUninit;
+use std::num::NonZeroUsize;
+use std::ptr;
+use std::thread;
+
+use crate::merge_sort::{merge_sort_impl, MERGE_SORT_THRESHOLD};
+use crate::probe::NoProbe;
+use crate::quick_sort::{quick_sort_impl, Partitioner};
+use crate::random::Rng;
+
+/// Subarrays shorter than this are sorted by a single thread by default.
+pub const PAR_MIN_LEN: usize = 1 << 12;
+
+/// Limits of the parallelism of a sort.
+///
+/// The default one uses all the available cores: `Parallelism { threads: 4, ..Parallelism::default() }` overrides only the number of threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parallelism {
+    /// Maximum number of threads working at the same time, including the calling one. 0 and 1 make the sort sequential.
+    pub threads: usize,
+    /// Subarrays shorter than this aren't divided between threads.
+    pub min_len: usize,
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Parallelism {
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            min_len: PAR_MIN_LEN,
+        }
+    }
+}
+
+impl Parallelism {
+    /// Returns whether `len` elements are to be divided between threads.
+    fn is_parallel(&self, len: usize) -> bool {
+        self.threads > 1 && len >= self.min_len.max(2)
+    }
+
+    /// Divides the threads between two parts of work of `first` and `second` elements, the first one is done by a spawned thread.
+    ///
+    /// Threads are divided in proportion to the lengths, but each part gets at least one of them, so no more than `threads` threads
+    /// work at the same time.
+    fn split(&self, first: usize, second: usize) -> (Parallelism, Parallelism) {
+        let total = (first + second).max(1) as u128;
+        let proportional = (self.threads as u128 * first as u128 + total / 2) / total;
+        let spawned = Parallelism {
+            threads: (proportional as usize).clamp(1, self.threads - 1),
+            ..*self
+        };
+        let current = Parallelism {
+            threads: self.threads - spawned.threads,
+            ..*self
+        };
+        (spawned, current)
+    }
+}
+
+/// Parallel merge sort implementation.
+///
+/// The halves are sorted by separate threads recursively, until the threads run out or subarrays become shorter than `parallelism.min_len`,
+/// then sequential [merge_sort](../merge_sort/fn.merge_sort.html) is used. The sorted halves are merged to a buffer by
+/// [P-MERGE](fn.par_merge_sort.html#p-merge) and moved back. With *p* threads it takes Θ(n*log n / p) time, until the memory bandwidth
+/// becomes the bottleneck, and O(n) additional memory.
+///
+/// Like [merge_sort](../merge_sort/fn.merge_sort.html), it's stable, moves elements instead of cloning them and is panic-safe: if the comparison
+/// panics, the panic is propagated after all the threads are joined, and `src` is left with the same elements in some order.
+///
+/// # P-MERGE
+///
+/// To merge sorted `left` and `right` in parallel, the median `x` of the longer of them is found, and the position of `x` in the other one
+/// is found by binary search. Elements less than `x` from both of them go before `x` in the output, the rest go after it, so these two
+/// merges are independent and done by separate threads recursively. Each of these merges gets at most 3/4 of the elements,
+/// so there are O(log n) levels of splits.
+///
+/// ```
+/// use clrs_algos::parallel::{par_merge_sort, Parallelism};
+///
+/// let mut src: Vec<u64> = (0..100_000).map(|i| i * 7_919 % 100_003).collect();
+/// par_merge_sort(&mut src, Parallelism::default());
+/// assert!(src.windows(2).all(|w| w[0] <= w[1]));
+/// ```
+pub fn par_merge_sort<T: PartialOrd + Send + Sync>(src: &mut [T], parallelism: Parallelism) {
+    par_merge_sort_impl(src, parallelism, &T::lt)
+}
+
+/// Same as [par_merge_sort](fn.par_merge_sort.html), but elements are compared by `compare`.
+pub fn par_merge_sort_by<T: Send + Sync, F: Fn(&T, &T) -> Ordering + Sync>(src: &mut [T], parallelism: Parallelism, compare: F) {
+    par_merge_sort_impl(src, parallelism, &|a, b| compare(a, b) == Ordering::Less)
+}
+
+/// Same as [par_merge_sort](fn.par_merge_sort.html), but elements are compared by keys extracted with `key`.
+pub fn par_merge_sort_by_key<T: Send + Sync, K: Ord, F: Fn(&T) -> K + Sync>(src: &mut [T], parallelism: Parallelism, key: F) {
+    par_merge_sort_impl(src, parallelism, &|a, b| key(a) < key(b))
+}
+
+pub(crate) fn par_merge_sort_impl<T: Send + Sync, F: Fn(&T, &T) -> bool + Sync>(src: &mut [T], parallelism: Parallelism, is_less: &F) {
+    if !parallelism.is_parallel(src.len()) {
+        merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut |a, b| is_less(a, b), &NoProbe);
+        return;
+    }
+    let mut buffer: Vec<T> = Vec::with_capacity(src.len());
+    // the buffer's length stays 0, so the elements copied to it are never dropped by it
+    let buffer = &mut buffer.spare_capacity_mut()[..src.len()];
+    par_merge_sort_recursive(src, buffer, parallelism, is_less);
+}
+
+/// Sorts `src` using `buffer` of the same length for merging.
+fn par_merge_sort_recursive<T: Send + Sync, F: Fn(&T, &T) -> bool + Sync>(src: &mut [T], buffer: &mut [MaybeUninit<T>], parallelism: Parallelism, is_less: &F) {
+    if !parallelism.is_parallel(src.len()) {
+        merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut |a, b| is_less(a, b), &NoProbe);
+        return;
+    }
+    let mid = src.len().div_ceil(2);
+    let (spawned, current) = parallelism.split(mid, src.len() - mid);
+    {
+        let (left, right) = src.split_at_mut(mid);
+        let (left_buffer, right_buffer) = buffer.split_at_mut(mid);
+        thread::scope(|scope| {
+            scope.spawn(|| par_merge_sort_recursive(left, left_buffer, spawned, is_less));
+            par_merge_sort_recursive(right, right_buffer, current, is_less);
+        });
+    }
+    let (left, right) = src.split_at(mid);
+    par_merge(left, right, buffer, parallelism, is_less);
+    // SAFETY: `buffer` is filled with bitwise copies of all the elements of `src` in the sorted order. `src` elements are overwritten
+    // without being dropped, so each element is owned by `src` exactly once again, and the copies in `buffer` are never used.
+    unsafe { ptr::copy_nonoverlapping(buffer.as_ptr() as *const T, src.as_mut_ptr(), src.len()) }
+}
+
+/// P-MERGE: merges sorted `left` and `right` to `dst` of their total length, see [par_merge_sort](fn.par_merge_sort.html#p-merge).
+///
+/// Elements are copied bitwise, and `left` and `right` aren't modified. So if `is_less` panics, the inputs still own all the elements,
+/// and the copies in `dst` are never dropped, because it's `MaybeUninit`.
+fn par_merge<T: Send + Sync, F: Fn(&T, &T) -> bool + Sync>(left: &[T], right: &[T], dst: &mut [MaybeUninit<T>], parallelism: Parallelism, is_less: &F) {
+    if !parallelism.is_parallel(dst.len()) {
+        merge_to(left, right, dst, is_less);
+        return;
+    }
+    // equal elements of `left` must go before ones of `right` for the stability, so the split position in `right` is
+    // the first element not less than `x`, and the split position in `left` is the first element greater than `x`
+    let (left_split, right_split, x, left_rest, right_rest) = if left.len() >= right.len() {
+        let mid = left.len() / 2;
+        let x = &left[mid];
+        let right_split = right.partition_point(|y| is_less(y, x));
+        (mid, right_split, x, mid + 1, right_split)
+    } else {
+        let mid = right.len() / 2;
+        let x = &right[mid];
+        let left_split = left.partition_point(|y| !is_less(x, y));
+        (left_split, mid, x, left_split, mid + 1)
+    };
+    let x_pos = left_split + right_split;
+    // SAFETY: a bitwise copy, see the function docs
+    dst[x_pos].write(unsafe { ptr::read(x) });
+
+    let (spawned, current) = parallelism.split(x_pos, dst.len() - x_pos - 1);
+    let (dst_low, dst_high) = dst.split_at_mut(x_pos);
+    let dst_high = &mut dst_high[1..];
+    thread::scope(|scope| {
+        scope.spawn(|| par_merge(&left[..left_split], &right[..right_split], dst_low, spawned, is_less));
+        par_merge(&left[left_rest..], &right[right_rest..], dst_high, current, is_less);
+    });
+}
+
+/// Sequentially merges sorted `left` and `right` to `dst` copying elements bitwise, like `par_merge`.
+fn merge_to<T, F: Fn(&T, &T) -> bool>(left: &[T], right: &[T], dst: &mut [MaybeUninit<T>], is_less: &F) {
+    let (mut i, mut j) = (0, 0);
+    for slot in dst.iter_mut() {
+        // the right element is taken only if it's less, so equal elements keep their order
+        let smallest = if i == left.len() || (j < right.len() && is_less(&right[j], &left[i])) {
+            j += 1;
+            &right[j - 1]
+        } else {
+            i += 1;
+            &left[i - 1]
+        };
+        // SAFETY: a bitwise copy, see `par_merge` docs
+        slot.write(unsafe { ptr::read(smallest) });
+    }
+}
+
+/// Parallel quick sort implementation.
+///
+/// After partitioning, the left and the right areas are disjoint, so they are sorted by separate threads recursively, until the threads
+/// run out or areas become shorter than `parallelism.min_len`. Then sequential [quick_sort](../quick_sort/fn.quick_sort.html) is used.
+/// Threads are divided between the areas in proportion to their lengths, because partitioning is rarely balanced.
+///
+/// The partitioning of the whole input is done by a single thread, so it takes Θ(n) time before any other thread starts.
+/// Hence the speedup is lower than for [par_merge_sort](fn.par_merge_sort.html), but no additional memory is used.
+///
+/// Any `partitioner` can be used, and the result is exactly the same as of [quick_sort](../quick_sort/fn.quick_sort.html),
+/// including the order of equal elements, because each area is partitioned the same way. The only exception is
+/// [Partitioner::Randomized](../quick_sort/enum.Partitioner.html#variant.Randomized): each spawned thread has its own random numbers generator
+/// seeded by the parent one, so pivots differ from the sequential sort. The result is sorted all the same, but equal elements can be
+/// in a different order.
+pub fn par_quick_sort<T: PartialOrd + Send>(src: &mut [T], partitioner: Partitioner, parallelism: Parallelism) {
+    par_quick_sort_impl(src, partitioner, parallelism, &mut partitioner.rng(), &T::lt)
+}
+
+/// Same as [par_quick_sort](fn.par_quick_sort.html), but elements are compared by `compare`.
+pub fn par_quick_sort_by<T: Send, F: Fn(&T, &T) -> Ordering + Sync>(src: &mut [T], partitioner: Partitioner, parallelism: Parallelism, compare: F) {
+    par_quick_sort_impl(src, partitioner, parallelism, &mut partitioner.rng(), &|a, b| compare(a, b) == Ordering::Less)
+}
+
+/// Same as [par_quick_sort](fn.par_quick_sort.html), but elements are compared by keys extracted with `key`.
+pub fn par_quick_sort_by_key<T: Send, K: Ord, F: Fn(&T) -> K + Sync>(src: &mut [T], partitioner: Partitioner, parallelism: Parallelism, key: F) {
+    par_quick_sort_impl(src, partitioner, parallelism, &mut partitioner.rng(), &|a, b| key(a) < key(b))
+}
+
+pub(crate) fn par_quick_sort_impl<T: Send, F: Fn(&T, &T) -> bool + Sync>(
+    src: &mut [T],
+    partitioner: Partitioner,
+    parallelism: Parallelism,
+    rng: &mut Rng,
+    is_less: &F,
+) {
+    // `quick_sort_impl` sorts 2 elements without partitioning, so they are left to it to get the same order of equal elements
+    if src.len() < 3 || !parallelism.is_parallel(src.len()) {
+        quick_sort_impl(src, partitioner, rng, &mut |a, b| is_less(a, b), &NoProbe);
+        return;
+    }
+    let (end_left, start_right) = partitioner.partition(src, rng, &mut |a, b| is_less(a, b), &NoProbe);
+    let (left, right) = src.split_at_mut(start_right);
+    let left = &mut left[..end_left];
+    let (spawned, current) = parallelism.split(left.len(), right.len());
+    let mut spawned_rng = Rng::new(rng.next_u64());
+    thread::scope(|scope| {
+        scope.spawn(|| par_quick_sort_impl(left, partitioner, spawned, &mut spawned_rng, is_less));
+        par_quick_sort_impl(right, partitioner, current, rng, is_less);
+    });
+}
+
+#[test]
+fn par_merge_sort_test() {
+    use crate::merge_sort::merge_sort;
+    use crate::test_utils::{get_random_vector, get_records, is_sorted_stably, test_sorting_algorithm, test_sorting_algorithm_by};
+
+    // tiny subarrays are divided between threads, so test vectors exercise all the corner cases of the splits
+    const TINY: Parallelism = Parallelism { threads: 8, min_len: 0 };
+    assert!(test_sorting_algorithm(|src| par_merge_sort(src, TINY)).is_ok());
+    assert!(test_sorting_algorithm_by(
+        |src, compare| par_merge_sort_by(src, Parallelism { threads: 3, min_len: 2 }, compare),
+        |src, key| par_merge_sort_by_key(src, Parallelism { threads: 3, min_len: 2 }, key)
+    )
+    .is_ok());
+
+    for &len in &[0, 1, 2, 3, 100, 1_000, 10_001, 100_000] {
+        for &max in &[3, u32::MAX] {
+            let input = get_random_vector(len, max, len as u64);
+            let mut expected = input.clone();
+            merge_sort(&mut expected);
+            for &threads in &[0, 1, 2, 3, 4, 16] {
+                for &min_len in &[0, 16, PAR_MIN_LEN] {
+                    let mut src = input.clone();
+                    par_merge_sort(&mut src, Parallelism { threads, min_len });
+                    assert_eq!(src, expected, "len: {}, threads: {}, min_len: {}", len, threads, min_len);
+                }
+            }
+        }
+    }
+    let mut src: Vec<u32> = (0..50_000).rev().collect();
+    par_merge_sort(&mut src, Parallelism::default());
+    assert!(src.iter().copied().eq(0..50_000));
+
+    // stability: records with equal keys keep the order of their indices, both splits of P-MERGE meet equal keys
+    let mut records = get_records(get_random_vector(10_001, 10, 19));
+    par_merge_sort_by_key(&mut records, TINY, |&(key, _)| key);
+    assert!(is_sorted_stably(&records));
+}
+
+#[test]
+fn par_merge_test() {
+    let parallelism = Parallelism { threads: 4, min_len: 0 };
+    for (left, right) in [
+        (vec![1, 2, 2, 5], vec![2, 2, 3]),
+        (vec![], vec![1, 2]),
+        (vec![4], vec![1, 2, 3, 4, 5, 6]),
+        (vec![2; 5], vec![2; 6]),
+    ]
+    .iter()
+    {
+        // keys are compared, the second value shows the origin
+        let left: Vec<(i32, bool)> = left.iter().map(|&key| (key, false)).collect();
+        let right: Vec<(i32, bool)> = right.iter().map(|&key| (key, true)).collect();
+        let mut expected: Vec<(i32, bool)> = left.iter().chain(right.iter()).copied().collect();
+        expected.sort_by_key(|&(key, _)| key);
+
+        let mut buffer = Vec::with_capacity(expected.len());
+        let dst = &mut buffer.spare_capacity_mut()[..expected.len()];
+        par_merge(&left, &right, dst, parallelism, &|a: &(i32, bool), b: &(i32, bool)| a.0 < b.0);
+        // SAFETY: all the elements are initialized by the merge, and they are `Copy`
+        let merged: Vec<(i32, bool)> = dst.iter().map(|value| unsafe { value.assume_init() }).collect();
+        assert_eq!(merged, expected);
+    }
+}
+
+#[test]
+fn par_merge_sort_panic_safety_test() {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+    use crate::test_utils::{get_random_vector, Droppable};
+
+    let len = 2_000;
+    let keys = get_random_vector(len, 1_000, 20);
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort_unstable();
+    for &limit in &[0, 100, 5_000, 15_000, 100_000] {
+        let drops = AtomicUsize::new(0);
+        let mut src: Vec<Droppable> = keys.iter().map(|&key| Droppable { key, drops: &drops }).collect();
+        let comparisons = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            par_merge_sort_by(&mut src, Parallelism { threads: 4, min_len: 64 }, |a, b| {
+                assert!(comparisons.fetch_add(1, Relaxed) < limit, "comparator failed");
+                a.key.cmp(&b.key)
+            })
+        }));
+        assert_eq!(result.is_ok(), limit == 100_000);
+        // all the elements are still there, none of them is dropped or duplicated
+        assert_eq!(drops.load(Relaxed), 0);
+        let mut keys_after: Vec<u32> = src.iter().map(|value| value.key).collect();
+        keys_after.sort_unstable();
+        assert_eq!(keys_after, sorted_keys);
+        drop(src);
+        assert_eq!(drops.load(Relaxed), len);
+    }
+}
+
+#[test]
+fn par_quick_sort_test() {
+    use crate::quick_sort::{quick_sort, quick_sort_by_key, Scheme};
+    use crate::test_utils::{get_random_vector, get_records, get_test_vectors, test_sorting_algorithm_by};
+
+    let partitioners = [
+        Partitioner::Lomuto,
+        Partitioner::Hoare,
+        Partitioner::ThreeWay,
+        Partitioner::Randomized {
+            scheme: Scheme::Lomuto,
+            seed: 5,
+        },
+        Partitioner::MedianOfThree(Scheme::Hoare),
+        Partitioner::Ninther(Scheme::ThreeWay),
+        Partitioner::MedianOfMedians(Scheme::Lomuto),
+    ];
+    let tiny = Parallelism { threads: 8, min_len: 0 };
+    assert!(test_sorting_algorithm_by(
+        |src, compare| par_quick_sort_by(src, Partitioner::Hoare, Parallelism { threads: 3, min_len: 2 }, compare),
+        |src, key| par_quick_sort_by_key(src, Partitioner::ThreeWay, Parallelism { threads: 3, min_len: 2 }, key)
+    )
+    .is_ok());
+
+    let mut inputs: Vec<Vec<u32>> = get_test_vectors()
+        .iter()
+        .map(|(input, _)| input.iter().map(|&value| value as u32).collect())
+        .collect();
+    // Lomuto's partitioning is quadratic with many duplicates, so they are moderate here
+    inputs.push(get_random_vector(100_000, u32::MAX, 21));
+    inputs.push(get_random_vector(20_000, 2_000, 22));
+    for &partitioner in &partitioners {
+        for input in inputs.iter() {
+            let mut expected = input.clone();
+            quick_sort(&mut expected, partitioner);
+            for &parallelism in &[
+                tiny,
+                Parallelism { threads: 1, min_len: 0 },
+                Parallelism { threads: 3, min_len: 100 },
+                Parallelism::default(),
+            ] {
+                let mut src = input.clone();
+                par_quick_sort(&mut src, partitioner, parallelism);
+                assert_eq!(src, expected, "{:?}, {:?}", partitioner, parallelism);
+            }
+        }
+    }
+
+    // records with equal keys end up in the same order as after the sequential sort
+    let records = get_records(get_random_vector(10_000, 500, 24));
+    for &partitioner in partitioners.iter().filter(|partitioner| !matches!(partitioner, Partitioner::Randomized { .. })) {
+        let mut expected = records.clone();
+        quick_sort_by_key(&mut expected, partitioner, |&(key, _)| key);
+        let mut src = records.clone();
+        par_quick_sort_by_key(&mut src, partitioner, tiny, |&(key, _)| key);
+        assert_eq!(src, expected, "{:?}", partitioner);
+    }
+
+    let pair = Parallelism { threads: 2, min_len: 0 };
+    let mut src = [(1, 'a'), (1, 'b')];
+    par_quick_sort_by_key(&mut src, Partitioner::Hoare, pair, |&(key, _)| key);
+    assert_eq!(src, [(1, 'a'), (1, 'b')]);
+
+    // all the short records with keys of 0..3, which exercise the smallest areas handed to threads, for every scheme with any pivot
+    let scheme_partitioner = |scheme| match scheme {
+        Scheme::Lomuto => Partitioner::Lomuto,
+        Scheme::Hoare => Partitioner::Hoare,
+        Scheme::ThreeWay => Partitioner::ThreeWay,
+    };
+    for &scheme in &[Scheme::Lomuto, Scheme::Hoare, Scheme::ThreeWay] {
+        let partitioners = [
+            scheme_partitioner(scheme),
+            Partitioner::MedianOfThree(scheme),
+            Partitioner::Ninther(scheme),
+            Partitioner::MedianOfMedians(scheme),
+        ];
+        for len in 0..=6 {
+            for code in 0..3u32.pow(len) {
+                let records = get_records((0..len).map(|digit| code / 3u32.pow(digit) % 3));
+                for &partitioner in &partitioners {
+                    let mut expected = records.clone();
+                    quick_sort_by_key(&mut expected, partitioner, |&(key, _)| key);
+                    let mut src = records.clone();
+                    par_quick_sort_by_key(&mut src, partitioner, pair, |&(key, _)| key);
+                    assert_eq!(src, expected, "{:?}", partitioner);
+                }
+            }
+        }
+    }
+}
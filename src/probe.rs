@@ -0,0 +1,217 @@
+//! Instrumentation of sorting algorithms.
+//!
+//! Asymptotic complexity says how the work grows with the input, but it's much more illustrative to see the actual work. For example, to see
+//! that insertion sort does *n(n-1)/2* comparisons on reverse sorted input, or that Hoare's partitioning does fewer swaps than Lomuto's one.
+//!
+//! Algorithms report primitive operations to a [Probe](trait.Probe.html): comparisons, swaps, writes and clones of elements, entering and
+//! leaving recursive calls. Some steps specific to an algorithm are reported as well: partitioning, sifting down the heap and merging.
+//! Elements are passed to the probe by reference, so a probe, which knows the input slice, can find their positions.
+//! Regular sorting functions use [NoProbe](struct.NoProbe.html), methods of which do nothing and are optimized away, so they don't pay
+//! for the instrumentation. [Sorter::sort_with_stats](../sorter/trait.Sorter.html#method.sort_with_stats) counts operations with
+//! a probe and returns [SortStats](struct.SortStats.html).
+
+use std::cell::Cell;
+
+/// Receiver of primitive operations performed by a sorting algorithm.
+///
+/// All the methods do nothing by default. They take `&self`, because a probe is shared between the algorithm and the comparator,
+/// so implementations use interior mutability.
+pub trait Probe<T> {
+    /// `a` and `b` are compared.
+    fn compare(&self, _a: &T, _b: &T) {}
+
+    /// Elements at `a` and `b` positions are going to be swapped.
+    fn swap(&self, _a: &T, _b: &T) {}
+
+    /// An element is moved (or copied) to the position of `dst`. The position can be in the input or in some temporary buffer.
+    fn write(&self, _dst: &T) {}
+
+    /// `src` is cloned.
+    fn cloned(&self, _src: &T) {}
+
+    /// A recursive call starts.
+    fn enter(&self) {}
+
+    /// A recursive call ends.
+    fn leave(&self) {}
+
+    /// `src` is partitioned into `src[..end_left]` and `src[start_right..]` areas, the pivot (or all the elements equal to it) is in between.
+    fn partition(&self, _src: &[T], _end_left: usize, _start_right: usize) {}
+
+    /// Max heapify moves `parent` down the heap swapping it with its larger `child`.
+    fn sift(&self, _parent: &T, _child: &T) {}
+
+    /// Sorted `src[..mid]` and `src[mid..]` are going to be merged.
+    fn merge(&self, _src: &[T], _mid: usize) {}
+}
+
+/// Probe that ignores all the operations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoProbe;
+
+impl<T> Probe<T> for NoProbe {}
+
+/// Numbers of primitive operations performed while sorting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    pub comparisons: u64,
+    pub swaps: u64,
+    pub clones: u64,
+    /// Elements moved or copied to some position (swaps are not counted here).
+    pub moves: u64,
+    /// The maximum number of nested recursive calls, 0 for iterative algorithms.
+    pub max_depth: usize,
+}
+
+/// Probe counting operations to [SortStats](struct.SortStats.html).
+#[derive(Debug, Default)]
+pub(crate) struct StatsProbe {
+    comparisons: Cell<u64>,
+    swaps: Cell<u64>,
+    clones: Cell<u64>,
+    moves: Cell<u64>,
+    depth: Cell<usize>,
+    max_depth: Cell<usize>,
+}
+
+impl StatsProbe {
+    pub(crate) fn stats(&self) -> SortStats {
+        SortStats {
+            comparisons: self.comparisons.get(),
+            swaps: self.swaps.get(),
+            clones: self.clones.get(),
+            moves: self.moves.get(),
+            max_depth: self.max_depth.get(),
+        }
+    }
+}
+
+impl<T> Probe<T> for StatsProbe {
+    fn compare(&self, _a: &T, _b: &T) {
+        self.comparisons.set(self.comparisons.get() + 1);
+    }
+
+    fn swap(&self, _a: &T, _b: &T) {
+        self.swaps.set(self.swaps.get() + 1);
+    }
+
+    fn write(&self, _dst: &T) {
+        self.moves.set(self.moves.get() + 1);
+    }
+
+    fn cloned(&self, _src: &T) {
+        self.clones.set(self.clones.get() + 1);
+    }
+
+    fn enter(&self) {
+        self.depth.set(self.depth.get() + 1);
+        self.max_depth.set(self.max_depth.get().max(self.depth.get()));
+    }
+
+    fn leave(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+}
+
+/// Swaps `src[i]` and `src[j]` reporting it to `probe`.
+pub(crate) fn swap<T, P: Probe<T> + ?Sized>(src: &mut [T], i: usize, j: usize, probe: &P) {
+    probe.swap(&src[i], &src[j]);
+    src.swap(i, j);
+}
+
+/// Returns `<` comparator of `T`, which reports comparisons to `probe`.
+pub(crate) fn probed_lt<T: PartialOrd, P: Probe<T> + ?Sized>(probe: &P) -> impl FnMut(&T, &T) -> bool + '_ {
+    move |a, b| {
+        probe.compare(a, b);
+        a < b
+    }
+}
+
+#[test]
+fn insertion_sort_stats_test() {
+    use crate::sorter::{InsertionSort, Sorter};
+
+    // reverse sorted input is the worst case: each pair of elements is an inversion, which is fixed by a swap after a comparison
+    for &n in &[100u64, 200, 400] {
+        let mut src: Vec<u64> = (0..n).rev().collect();
+        let stats = InsertionSort.sort_with_stats(&mut src).expect("insertion sort doesn't fail");
+        assert!(src.iter().copied().eq(0..n));
+        let pairs = n * (n - 1) / 2;
+        let expected = SortStats {
+            comparisons: pairs,
+            swaps: pairs,
+            ..SortStats::default()
+        };
+        assert_eq!(stats, expected);
+    }
+
+    // sorted input is the best case
+    let mut src: Vec<u64> = (0..400).collect();
+    let stats = InsertionSort.sort_with_stats(&mut src).expect("insertion sort doesn't fail");
+    assert_eq!(stats.comparisons, 399);
+    assert_eq!(stats.swaps, 0);
+}
+
+#[test]
+fn partitioning_stats_test() {
+    use crate::quick_sort::Partitioner;
+    use crate::sorter::{QuickSort, QuickSortTailRecursive, Sorter};
+    use crate::test_utils::get_random_vector;
+
+    let input = get_random_vector(10_000, u32::MAX, 7);
+    let mut src = input.clone();
+    let lomuto = QuickSort(Partitioner::Lomuto).sort_with_stats(&mut src).expect("quick sort doesn't fail");
+    let mut src = input.clone();
+    let hoare = QuickSort(Partitioner::Hoare).sort_with_stats(&mut src).expect("quick sort doesn't fail");
+    // Lomuto's partitioning swaps every element not greater than the pivot, Hoare's one swaps only misplaced pairs
+    assert!(hoare.swaps < lomuto.swaps, "Hoare: {:?}, Lomuto: {:?}", hoare, lomuto);
+
+    // sorted input makes the recursion as deep as the input is long, unless the smaller part is chosen for it
+    let n = 1_000;
+    let mut src: Vec<u32> = (0..n).collect();
+    let stats = QuickSort(Partitioner::Lomuto).sort_with_stats(&mut src).expect("quick sort doesn't fail");
+    assert!(stats.max_depth >= n as usize - 2);
+    assert_eq!(stats.comparisons, u64::from(n) * u64::from(n - 1) / 2);
+    let stats = QuickSortTailRecursive(Partitioner::Lomuto)
+        .sort_with_stats(&mut src)
+        .expect("quick sort doesn't fail");
+    assert!(stats.max_depth <= 2);
+}
+
+#[test]
+fn registry_stats_test() {
+    use crate::sorter::{integer_sorters, MergeSort, Sorter};
+    use crate::test_utils::get_random_vector;
+
+    let input: Vec<i64> = get_random_vector(512, 1_000, 3).into_iter().map(i64::from).collect();
+    let mut sorted = input.clone();
+    sorted.sort_unstable();
+    for sorter in integer_sorters::<i64>() {
+        let mut src = input.clone();
+        let stats = sorter.sort_with_stats(&mut src).expect("sorting succeeds");
+        assert_eq!(src, sorted, "{:?} failed", sorter);
+        let metadata = sorter.metadata();
+        // comparison sorters need at least n - 1 comparisons, distribution sorts compare only to find the range of values
+        if metadata.name.starts_with("count") || metadata.name.starts_with("radix") {
+            assert!(stats.comparisons <= 3 * 512 / 2, "{:?}: {:?}", sorter, stats);
+            assert_eq!(stats.swaps, 0, "{:?}: {:?}", sorter, stats);
+        } else {
+            assert!(stats.comparisons >= 511, "{:?}: {:?}", sorter, stats);
+        }
+        // algorithms, which don't use additional memory for elements, only swap them
+        if metadata.in_place {
+            assert_eq!(stats.moves, 0, "{:?}: {:?}", sorter, stats);
+            assert_eq!(stats.clones, 0, "{:?}: {:?}", sorter, stats);
+        } else {
+            assert!(stats.moves >= 512, "{:?}: {:?}", sorter, stats);
+        }
+    }
+
+    // On each of log2(512) = 9 levels, but the last one with 2 elements arrays, merge sort moves the left halves to the buffer
+    // and back, and the right halves are moved only if they aren't in their places
+    let mut src = input.clone();
+    let stats = MergeSort.sort_with_stats(&mut src).expect("merge sort doesn't fail");
+    assert_eq!(stats.clones, 0);
+    assert!((512 * 8..=(256 + 512) * 8).contains(&stats.moves), "{:?}", stats);
+    assert_eq!(stats.max_depth, 9);
+}
@@ -12,10 +12,14 @@
 
 use std::cmp::Ordering;
 
+use crate::probe::{self, NoProbe, Probe};
+use crate::random::Rng;
+use crate::select::median_of_medians;
+
 /// Partitioner providing different types of partitioning.
 ///
 /// The core of the quick sort is partitioning. We can implement different partitioning algorithms, which should follow the idea stated in the module [doc](index.html).
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Partitioner {
     /// Partitioning algorithm provided by Nico Lomuto.
     ///
@@ -32,6 +36,53 @@ pub enum Partitioner {
     /// placing it to some position. The main thing is to form 2 subarrays where values of the left one are less than values of the right one. However, Hoare's
     /// algorithm returns the index of the first element of the second array.
     Hoare,
+    /// Three-way partitioning, also known as Dijkstra's "Dutch national flag" problem.
+    ///
+    /// Both algorithms above handle equal keys poorly: if all the elements are equal, Lomuto's algorithm places all of them to the left array,
+    /// so we get Θ(n^2) time. Three-way partitioning forms 3 areas: elements less than the pivot, equal to the pivot and greater than the pivot.
+    /// Returned indices are the end of the "less" area and the start of the "greater" area, so the "equal" area, which is already sorted,
+    /// is skipped by the sorting procedure. The more duplicates there are, the less work is done: all-equal input is sorted in Θ(n).
+    ThreeWay,
+    /// Randomized version of the partitioning `scheme` (CLRS 7.3).
+    ///
+    /// Lomuto and Hoare algorithms always choose the pivot from the same position, so already sorted input (which is common)
+    /// leads to the most unbalanced partitions and Θ(n^2) time. Randomized partitioner swaps a randomly chosen element to the position
+    /// of the pivot expected by `scheme` before running it. So no particular input leads to the worst case, and the expected time is O(n*log n).
+    ///
+    /// Random numbers are generated by a pseudo random numbers generator initialized with `seed`, so sorts are reproducible.
+    Randomized { scheme: Scheme, seed: u64 },
+    /// Partitioning `scheme` with the pivot being the median of the first, the middle and the last elements.
+    ///
+    /// It's a deterministic alternative to the randomized partitioner: sorted and reverse sorted inputs become the best cases,
+    /// because the median of three is the median of the whole array. There are still inputs leading to Θ(n^2) time,
+    /// but they are quite special ("median-of-three killers").
+    MedianOfThree(Scheme),
+    /// Partitioning `scheme` with the pivot being Tukey's ninther, i.e. the median of three medians of three elements.
+    ///
+    /// Nine elements are sampled evenly across the array, so the pivot is closer to the real median than the median of three.
+    /// Arrays shorter than `NINTHER_THRESHOLD` elements use the median of three, because sampling costs more than it gives for them.
+    Ninther(Scheme),
+    /// Partitioning `scheme` with the pivot being the median of medians of groups of five elements (CLRS 9.3).
+    ///
+    /// The pivot is chosen in Θ(n) time by [select_nth_deterministic](../select/fn.select_nth_deterministic.html)'s procedure.
+    /// It's guaranteed that at least *3n/10 - 6* elements are on each side of the pivot, so the depth of recursion is O(log n)
+    /// and quick sort has Θ(n*log n) time in the worst case. Finding the pivot has a big constant factor though, so in practice
+    /// other partitioners are faster. Duplicates should be handled by [Scheme::ThreeWay](enum.Scheme.html#variant.ThreeWay).
+    MedianOfMedians(Scheme),
+}
+
+/// Arrays of this length or longer use Tukey's ninther in [Partitioner::Ninther](enum.Partitioner.html#variant.Ninther).
+pub const NINTHER_THRESHOLD: usize = 40;
+
+/// Partitioning scheme used by those partitioners, which choose the pivot in some special way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// See [Partitioner::Lomuto](enum.Partitioner.html#variant.Lomuto). The pivot is expected to be the last element.
+    Lomuto,
+    /// See [Partitioner::Hoare](enum.Partitioner.html#variant.Hoare). The pivot is expected to be the first element.
+    Hoare,
+    /// See [Partitioner::ThreeWay](enum.Partitioner.html#variant.ThreeWay). The pivot is expected to be the first element.
+    ThreeWay,
 }
 
 /// Quick sort algorithm implementation
@@ -39,21 +90,100 @@ pub enum Partitioner {
 /// Parametrized by `partitioner` function. Actually `partitioner` is an enum, but under the hood runs
 /// one of partitioning algorithms.
 pub fn quick_sort<T: PartialOrd + Clone>(src: &mut [T], partitioner: Partitioner) {
+    quick_sort_impl(src, partitioner, &mut partitioner.rng(), &mut T::lt, &NoProbe)
+}
+
+/// Same as [quick_sort](fn.quick_sort.html), but elements are compared by `compare`.
+pub fn quick_sort_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], partitioner: Partitioner, mut compare: F) {
+    quick_sort_impl(src, partitioner, &mut partitioner.rng(), &mut |a, b| compare(a, b) == Ordering::Less, &NoProbe)
+}
+
+/// Same as [quick_sort](fn.quick_sort.html), but elements are compared by keys extracted with `key`.
+pub fn quick_sort_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], partitioner: Partitioner, mut key: F) {
+    quick_sort_impl(src, partitioner, &mut partitioner.rng(), &mut |a, b| key(a) < key(b), &NoProbe)
+}
+
+pub(crate) fn quick_sort_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(
+    src: &mut [T],
+    partitioner: Partitioner,
+    rng: &mut Rng,
+    is_less: &mut F,
+    probe: &P,
+) {
+    probe.enter();
     match src.len() {
         0 | 1 => {}
         2 => {
-            if src[0] > src[1] {
-                src.swap(0, 1)
+            if is_less(&src[1], &src[0]) {
+                probe::swap(src, 0, 1, probe)
             }
         }
-        _ => quick_sort_impl(src, partitioner),
+        _ => {
+            let (end_left, start_right) = partitioner.partition(src, rng, is_less, probe);
+            quick_sort_impl(&mut src[..end_left], partitioner, rng, is_less, probe);
+            quick_sort_impl(&mut src[start_right..], partitioner, rng, is_less, probe);
+        }
     }
+    probe.leave();
+}
+
+/// Quick sort with O(log n) stack depth for any `partitioner` (CLRS Problem 7-4).
+///
+/// [quick_sort](fn.quick_sort.html) makes two recursive calls. If partitioning is unbalanced, e.g. Lomuto's partitioning of sorted input,
+/// the depth of recursion is Θ(n), which leads to stack overflow on large inputs (the same problem heap sort's `max_heapify` had).
+/// The second recursive call is a tail call, so we replace it with a loop. But to bound the depth we recurse into the smaller array
+/// and loop on the larger one. The smaller array has at most half of the elements, so each recursive call halves the input,
+/// and the depth is at most *log n*.
+///
+/// Note that it bounds only the stack depth, not the time: unbalanced partitioning is still Θ(n^2).
+pub fn quick_sort_tail_recursive<T: PartialOrd + Clone>(src: &mut [T], partitioner: Partitioner) {
+    quick_sort_tail_recursive_impl(src, partitioner, &mut partitioner.rng(), &mut T::lt, &NoProbe, 0);
 }
 
-fn quick_sort_impl<T: PartialOrd + Clone>(src: &mut [T], partitioner: Partitioner) {
-    let (end_left, start_right) = partitioner.run(src);
-    quick_sort(&mut src[..end_left], partitioner);
-    quick_sort(&mut src[start_right..], partitioner);
+/// Same as [quick_sort_tail_recursive](fn.quick_sort_tail_recursive.html), but elements are compared by `compare`.
+pub fn quick_sort_tail_recursive_by<T, F: FnMut(&T, &T) -> Ordering>(src: &mut [T], partitioner: Partitioner, mut compare: F) {
+    let is_less = &mut |a: &T, b: &T| compare(a, b) == Ordering::Less;
+    quick_sort_tail_recursive_impl(src, partitioner, &mut partitioner.rng(), is_less, &NoProbe, 0);
+}
+
+/// Same as [quick_sort_tail_recursive](fn.quick_sort_tail_recursive.html), but elements are compared by keys extracted with `key`.
+pub fn quick_sort_tail_recursive_by_key<T, K: Ord, F: FnMut(&T) -> K>(src: &mut [T], partitioner: Partitioner, mut key: F) {
+    quick_sort_tail_recursive_impl(src, partitioner, &mut partitioner.rng(), &mut |a: &T, b: &T| key(a) < key(b), &NoProbe, 0);
+}
+
+/// Returns the maximum depth of recursion reached while sorting `src`, where `depth` is the depth of the current call.
+pub(crate) fn quick_sort_tail_recursive_impl<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(
+    mut src: &mut [T],
+    partitioner: Partitioner,
+    rng: &mut Rng,
+    is_less: &mut F,
+    probe: &P,
+    depth: usize,
+) -> usize {
+    probe.enter();
+    let mut max_depth = depth;
+    loop {
+        match src.len() {
+            0 | 1 => break,
+            2 => {
+                if is_less(&src[1], &src[0]) {
+                    probe::swap(src, 0, 1, probe)
+                }
+                break;
+            }
+            _ => {
+                let (end_left, start_right) = partitioner.partition(src, rng, is_less, probe);
+                let (left, right) = src.split_at_mut(start_right);
+                let left = &mut left[..end_left];
+                // recursing into the smaller one, looping on the larger one
+                let (smaller, larger) = if left.len() < right.len() { (left, right) } else { (right, left) };
+                max_depth = max_depth.max(quick_sort_tail_recursive_impl(smaller, partitioner, rng, is_less, probe, depth + 1));
+                src = larger;
+            }
+        }
+    }
+    probe.leave();
+    max_depth
 }
 
 impl Partitioner {
@@ -68,20 +198,111 @@ impl Partitioner {
     /// # let end_left = 1;
     /// let left_array = &mut src[..end_left];
     /// ```
+    ///
+    /// Randomized partitioner initializes a new random numbers generator with its seed on each call.
     pub fn run<T: PartialOrd + Clone>(self, src: &mut [T]) -> (usize, usize) {
+        self.partition(src, &mut self.rng(), &mut T::lt, &NoProbe)
+    }
+
+    /// Same as [run](#method.run), but random numbers are taken from `rng`, which lives through all the sort, and elements are compared by `is_less`.
+    pub(crate) fn partition<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(
+        self,
+        src: &mut [T],
+        rng: &mut Rng,
+        is_less: &mut F,
+        probe: &P,
+    ) -> (usize, usize) {
+        let (end_left, start_right) = match self {
+            Partitioner::Lomuto => Scheme::Lomuto.run(src, is_less, probe),
+            Partitioner::Hoare => Scheme::Hoare.run(src, is_less, probe),
+            Partitioner::ThreeWay => Scheme::ThreeWay.run(src, is_less, probe),
+            Partitioner::Randomized { scheme, .. } => {
+                let pivot_idx = rng.gen_range(src.len());
+                scheme.run_with_pivot(src, pivot_idx, is_less, probe)
+            }
+            Partitioner::MedianOfThree(scheme) => {
+                let pivot_idx = median_of_three(src, 0, src.len() / 2, src.len() - 1, is_less);
+                scheme.run_with_pivot(src, pivot_idx, is_less, probe)
+            }
+            Partitioner::Ninther(scheme) => {
+                let pivot_idx = ninther(src, is_less);
+                scheme.run_with_pivot(src, pivot_idx, is_less, probe)
+            }
+            Partitioner::MedianOfMedians(scheme) => {
+                let pivot_idx = median_of_medians(src, is_less, probe);
+                scheme.run_with_pivot(src, pivot_idx, is_less, probe)
+            }
+        };
+        probe.partition(src, end_left, start_right);
+        (end_left, start_right)
+    }
+
+    pub(crate) fn rng(self) -> Rng {
+        match self {
+            Partitioner::Randomized { seed, .. } => Rng::new(seed),
+            _ => Rng::new(0),
+        }
+    }
+}
+
+impl Scheme {
+    fn run<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(self, src: &mut [T], is_less: &mut F, probe: &P) -> (usize, usize) {
         match self {
-            Partitioner::Lomuto => {
-                let q = lomuto_partitioning(src);
+            Scheme::Lomuto => {
+                let q = lomuto_partitioning(src, is_less, probe);
                 (q, q + 1)
             }
-            Partitioner::Hoare => {
-                let q = hoare_partitioning(src);
+            Scheme::Hoare => {
+                let q = hoare_partitioning(src, is_less, probe);
                 (q + 1, q + 1)
             }
+            Scheme::ThreeWay => three_way_partitioning(src, is_less, probe),
+        }
+    }
+
+    /// Moves the element at `pivot_idx` to the position, where the scheme expects the pivot to be, and runs the scheme.
+    pub(crate) fn run_with_pivot<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(
+        self,
+        src: &mut [T],
+        pivot_idx: usize,
+        is_less: &mut F,
+        probe: &P,
+    ) -> (usize, usize) {
+        match self {
+            Scheme::Lomuto => probe::swap(src, pivot_idx, src.len() - 1, probe),
+            Scheme::Hoare | Scheme::ThreeWay => probe::swap(src, pivot_idx, 0, probe),
         }
+        self.run(src, is_less, probe)
     }
 }
 
+/// Returns the index of the median of `src[a]`, `src[b]` and `src[c]`.
+fn median_of_three<T, F: FnMut(&T, &T) -> bool>(src: &[T], a: usize, b: usize, c: usize, is_less: &mut F) -> usize {
+    // `a` and `b` are sorted, so `c` is either the smallest, the largest or is in the middle
+    let (a, b) = if is_less(&src[b], &src[a]) { (b, a) } else { (a, b) };
+    if is_less(&src[c], &src[a]) {
+        a
+    } else if is_less(&src[b], &src[c]) {
+        b
+    } else {
+        c
+    }
+}
+
+/// Returns the index of Tukey's ninther of `src`. Falls back to the median of three for short arrays.
+fn ninther<T, F: FnMut(&T, &T) -> bool>(src: &[T], is_less: &mut F) -> usize {
+    let last = src.len() - 1;
+    let mid = src.len() / 2;
+    if src.len() < NINTHER_THRESHOLD {
+        return median_of_three(src, 0, mid, last, is_less);
+    }
+    let step = src.len() / 8;
+    let first_median = median_of_three(src, 0, step, 2 * step, is_less);
+    let middle_median = median_of_three(src, mid - step, mid, mid + step, is_less);
+    let last_median = median_of_three(src, last - 2 * step, last - step, last, is_less);
+    median_of_three(src, first_median, middle_median, last_median, is_less)
+}
+
 /// Basically, it `greater_than_pivot_start` is the index of the first element if the right array.
 /// It means that last element of the left array is at index `greater_than_pivot_start - 1`.
 /// When less (or equal) than pivot element is found we place it to the end of the left array.
@@ -107,18 +328,18 @@ impl Partitioner {
 ///     num + 1
 /// }
 /// ```
-fn lomuto_partitioning<T: PartialOrd + Clone>(src: &mut [T]) -> usize {
+fn lomuto_partitioning<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) -> usize {
     let pivot_idx = src.len() - 1;
     let mut greater_than_pivot_start = 0;
     for greater_than_pivot_end in 0..pivot_idx {
-        if src[greater_than_pivot_end] <= src[pivot_idx] {
+        if !is_less(&src[pivot_idx], &src[greater_than_pivot_end]) {
             // increasing left area array by one and placing to it's end found element
             greater_than_pivot_start += 1;
-            src.swap(greater_than_pivot_start - 1, greater_than_pivot_end)
+            probe::swap(src, greater_than_pivot_start - 1, greater_than_pivot_end, probe)
         }
     }
     let proper_pivot_idx = greater_than_pivot_start;
-    src.swap(pivot_idx, proper_pivot_idx);
+    probe::swap(src, pivot_idx, proper_pivot_idx, probe);
     proper_pivot_idx
 }
 
@@ -160,20 +381,20 @@ fn lomuto_partitioning<T: PartialOrd + Clone>(src: &mut [T]) -> usize {
 ///     num + 1
 /// }
 /// ```
-fn hoare_partitioning<T: PartialOrd + Clone>(src: &mut [T]) -> usize {
+fn hoare_partitioning<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) -> usize {
     let mut pivot_idx = 0;
     let mut less_than_pivot_end = -1isize;
     let mut greater_than_pivot_start = src.len();
     loop {
         'g: loop {
             greater_than_pivot_start -= 1;
-            if src[greater_than_pivot_start] <= src[pivot_idx] {
+            if !is_less(&src[pivot_idx], &src[greater_than_pivot_start]) {
                 break 'g;
             }
         }
         'l: loop {
             less_than_pivot_end += 1;
-            if src[less_than_pivot_end as usize] >= src[pivot_idx] {
+            if !is_less(&src[less_than_pivot_end as usize], &src[pivot_idx]) {
                 break 'l;
             }
         }
@@ -184,7 +405,7 @@ fn hoare_partitioning<T: PartialOrd + Clone>(src: &mut [T]) -> usize {
                 if less_than_pivot_end as usize == pivot_idx {
                     pivot_idx = greater_than_pivot_start;
                 }
-                src.swap(less_than_pivot_end as usize, greater_than_pivot_start);
+                probe::swap(src, less_than_pivot_end as usize, greater_than_pivot_start, probe);
             }
             Ordering::Equal | Ordering::Greater => {
                 break greater_than_pivot_start;
@@ -193,6 +414,34 @@ fn hoare_partitioning<T: PartialOrd + Clone>(src: &mut [T]) -> usize {
     }
 }
 
+/// Partitions `src` to `[..less_end]`, `[less_end..greater_start]` and `[greater_start..]` areas with elements less, equal and greater than the pivot.
+///
+/// The pivot is the first element. Invariants of the loop are:
+/// - `src[..less_end]` are less than the pivot;
+/// - `src[less_end..current]` are equal to the pivot. The area isn't empty, because it has the pivot itself, so we compare with `src[less_end]`
+///   and don't need to clone the pivot value;
+/// - `src[current..greater_start]` are not checked yet;
+/// - `src[greater_start..]` are greater than the pivot.
+fn three_way_partitioning<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) -> (usize, usize) {
+    let mut less_end = 0;
+    let mut current = 1;
+    let mut greater_start = src.len();
+    while current < greater_start {
+        if is_less(&src[current], &src[less_end]) {
+            // the first equal element goes to the end of the "equal" area
+            probe::swap(src, less_end, current, probe);
+            less_end += 1;
+            current += 1;
+        } else if is_less(&src[less_end], &src[current]) {
+            greater_start -= 1;
+            probe::swap(src, current, greater_start, probe);
+        } else {
+            current += 1;
+        }
+    }
+    (less_end, greater_start)
+}
+
 #[test]
 fn quick_sort_test() {
     use crate::test_utils::get_test_vectors;
@@ -206,3 +455,223 @@ fn quick_sort_test() {
         assert_eq!(&mut input2, sorted);
     }
 }
+
+#[test]
+fn quick_sort_by_test() {
+    use crate::test_utils::test_sorting_algorithm_by;
+
+    assert!(test_sorting_algorithm_by(
+        |src, compare| quick_sort_by(src, Partitioner::Hoare, compare),
+        |src, key| quick_sort_by_key(src, Partitioner::Ninther(Scheme::ThreeWay), key)
+    )
+    .is_ok());
+    assert!(test_sorting_algorithm_by(
+        |src, compare| quick_sort_tail_recursive_by(
+            src,
+            Partitioner::Randomized {
+                scheme: Scheme::Lomuto,
+                seed: 1
+            },
+            compare
+        ),
+        |src, key| quick_sort_tail_recursive_by_key(src, Partitioner::MedianOfMedians(Scheme::Hoare), key)
+    )
+    .is_ok());
+}
+
+#[test]
+fn randomized_quick_sort_test() {
+    use crate::test_utils::get_test_vectors;
+
+    for &scheme in &[Scheme::Lomuto, Scheme::Hoare, Scheme::ThreeWay] {
+        for seed in 0..10 {
+            let partitioner = Partitioner::Randomized { scheme, seed };
+            for (input, sorted) in get_test_vectors().iter_mut() {
+                quick_sort(input, partitioner);
+                assert_eq!(input, sorted);
+            }
+        }
+
+        // sorted input is the worst case for not randomized partitioners
+        let mut src: Vec<u32> = (0..100_000).collect();
+        quick_sort(&mut src, Partitioner::Randomized { scheme, seed: 7 });
+        assert!(src.iter().copied().eq(0..100_000));
+    }
+
+    // same seed - same partitioning
+    let mut src1 = vec![5, 1, 9, 3, 7, 2, 8, 6, 4, 0];
+    let mut src2 = src1.clone();
+    let partitioner = Partitioner::Randomized {
+        scheme: Scheme::Lomuto,
+        seed: 42,
+    };
+    assert_eq!(partitioner.run(&mut src1), partitioner.run(&mut src2));
+    assert_eq!(src1, src2);
+}
+
+#[test]
+fn three_way_quick_sort_test() {
+    use crate::test_utils::get_test_vectors;
+
+    for (input, sorted) in get_test_vectors().iter_mut() {
+        quick_sort(input, Partitioner::ThreeWay);
+        assert_eq!(input, sorted);
+    }
+
+    let mut src = [3, 1, 3, 5, 3, 0, 3, 4];
+    assert_eq!(Partitioner::ThreeWay.run(&mut src), (2, 6));
+    assert!(src[..2].iter().all(|&v| v < 3));
+    assert!(src[2..6].iter().all(|&v| v == 3));
+    assert!(src[6..].iter().all(|&v| v > 3));
+
+    // low-cardinality input, which is Θ(n^2) for Lomuto's partitioning
+    let mut src: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 4) as u8).collect();
+    quick_sort(&mut src, Partitioner::ThreeWay);
+    assert!(src.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(src.iter().filter(|&&v| v == 0).count(), 50_000);
+
+    let mut src = vec![2; 1_000_000];
+    quick_sort(&mut src, Partitioner::ThreeWay);
+    assert_eq!(Partitioner::ThreeWay.run(&mut src), (0, 1_000_000));
+}
+
+#[test]
+fn quick_sort_tail_recursive_test() {
+    use crate::test_utils::get_test_vectors;
+
+    let partitioners = [
+        Partitioner::Lomuto,
+        Partitioner::Hoare,
+        Partitioner::ThreeWay,
+        Partitioner::Randomized {
+            scheme: Scheme::Hoare,
+            seed: 0,
+        },
+    ];
+    for &partitioner in &partitioners {
+        for (input, sorted) in get_test_vectors().iter_mut() {
+            quick_sort_tail_recursive(input, partitioner);
+            assert_eq!(input, sorted);
+        }
+    }
+
+    // Sorting is done in a thread with a small stack. Not bounded quick sort of sorted input with Lomuto's partitioning
+    // makes 10_000 nested calls, which overflows such stack both in debug and release builds. The input is not of several
+    // million elements, because with such pivot every partitioning leaves all but one element in the same part, so sorting
+    // takes quadratic time and would run for hours. Any pivot, which makes the recursion deep enough to overflow the
+    // stack, partitions almost the whole input on each of thousands of levels, so the stack is made small instead of the
+    // input large.
+    let handle = std::thread::Builder::new()
+        .stack_size(256 * 1024)
+        .spawn(|| {
+            let mut src: Vec<u32> = (0..10_000).collect();
+            let depth = quick_sort_tail_recursive_impl(&mut src, Partitioner::Lomuto, &mut Partitioner::Lomuto.rng(), &mut u32::lt, &NoProbe, 0);
+            assert!(src.iter().copied().eq(0..10_000));
+            // all partitions are maximally unbalanced, so recursive calls are done only for empty arrays
+            assert!(depth <= 1);
+
+            // several million elements are sorted in a reasonable time only with balanced partitions, so this checks
+            // the depth bound rather than the overflow
+            let n = 2_000_000;
+            let mut src: Vec<u32> = (0..n).collect();
+            let partitioner = Partitioner::Randomized {
+                scheme: Scheme::Lomuto,
+                seed: 1,
+            };
+            let depth = quick_sort_tail_recursive_impl(&mut src, partitioner, &mut partitioner.rng(), &mut u32::lt, &NoProbe, 0);
+            assert!(src.iter().copied().eq(0..n));
+            // log2(2_000_000) < 21
+            assert!(depth <= 21);
+        })
+        .expect("thread is spawned");
+    assert!(handle.join().is_ok());
+}
+
+#[test]
+fn median_pivots_quick_sort_test() {
+    use crate::test_utils::get_test_vectors;
+
+    for &scheme in &[Scheme::Lomuto, Scheme::Hoare, Scheme::ThreeWay] {
+        for &partitioner in &[Partitioner::MedianOfThree(scheme), Partitioner::Ninther(scheme)] {
+            for (input, sorted) in get_test_vectors().iter_mut() {
+                quick_sort(input, partitioner);
+                assert_eq!(input, sorted);
+            }
+
+            // sorted and reverse sorted inputs are the best cases now
+            let n = 100_000;
+            let mut src: Vec<u32> = (0..n).collect();
+            quick_sort(&mut src, partitioner);
+            assert!(src.iter().copied().eq(0..n));
+            let mut src: Vec<u32> = (0..n).rev().collect();
+            quick_sort(&mut src, partitioner);
+            assert!(src.iter().copied().eq(0..n));
+        }
+    }
+
+    assert_eq!(median_of_three(&[1, 2, 3], 0, 1, 2, &mut i32::lt), 1);
+    assert_eq!(median_of_three(&[3, 1, 2], 0, 1, 2, &mut i32::lt), 2);
+    assert_eq!(median_of_three(&[2, 3, 1], 0, 1, 2, &mut i32::lt), 0);
+    let src: Vec<u32> = (0..100).collect();
+    assert_eq!(ninther(&src, &mut u32::lt), 50);
+    let src: Vec<u32> = (0..100).rev().collect();
+    assert_eq!(ninther(&src, &mut u32::lt), 50);
+
+    // chosen pivot is placed to its final position
+    let mut src = [9, 4, 7, 5, 1, 8, 2];
+    assert_eq!(Partitioner::MedianOfThree(Scheme::Lomuto).run(&mut src), (3, 4));
+    assert_eq!(src[3], 5);
+}
+
+#[test]
+fn median_of_medians_quick_sort_test() {
+    use crate::merge_sort::merge_sort;
+    use crate::sorter::{QuickSort, Sorter};
+    use crate::test_utils::{get_random_vector, get_test_vectors};
+
+    for &scheme in &[Scheme::Lomuto, Scheme::Hoare, Scheme::ThreeWay] {
+        let partitioner = Partitioner::MedianOfMedians(scheme);
+        for (input, sorted) in get_test_vectors().iter_mut() {
+            quick_sort(input, partitioner);
+            assert_eq!(input, sorted);
+        }
+
+        let n = 20_000;
+        let inputs = [
+            get_random_vector(n, u32::MAX, 11),
+            (0..n as u32).collect(),
+            (0..n as u32).rev().collect(),
+            (0..n as u32).map(|i| i.min(n as u32 - i)).collect(),
+        ];
+        for input in inputs.iter() {
+            let mut src = input.clone();
+            let mut expected = input.clone();
+            quick_sort(&mut src, partitioner);
+            merge_sort(&mut expected);
+            assert_eq!(src, expected);
+        }
+    }
+
+    // the pivot is selected with three-way partitioning, so duplicates don't make the selection quadratic
+    for &scheme in &[Scheme::Hoare, Scheme::ThreeWay] {
+        for &n in &[1_000u32, 2_000, 4_000, 8_000] {
+            // all-equal and few distinct keys
+            for &keys in &[1, 3] {
+                let mut src: Vec<u32> = (0..n).map(|i| i % keys).collect();
+                let stats = QuickSort(Partitioner::MedianOfMedians(scheme))
+                    .sort_with_stats(&mut src)
+                    .expect("quick sort doesn't fail");
+                assert!(src.windows(2).all(|w| w[0] <= w[1]));
+                let log_n = 32 - n.leading_zeros();
+                assert!(
+                    stats.comparisons <= 3 * u64::from(n * log_n),
+                    "{:?}, n: {}, keys: {}: {:?}",
+                    scheme,
+                    n,
+                    keys,
+                    stats
+                );
+            }
+        }
+    }
+}
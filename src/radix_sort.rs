@@ -1,71 +1,287 @@
 //! Radix sort. With some conditions has a O(n) time complexity.
+//!
+//! Radix sort doesn't compare elements with each other. Instead it sorts numbers digit by digit starting from the least significant one (so it is
+//! called LSD radix sort). Each digit pass must be [stable](https://en.wikipedia.org/wiki/Sorting_algorithm#Stability): numbers with equal current
+//! digits should keep the order established by the previous passes. That's why after the last pass (the most significant digit) numbers are sorted.
+//!
+//! If we have *n* numbers with at most *d* digits in base *b*, then each pass costs *O(n + b)* and the whole sort costs *O(d * (n + b))*.
+//! When *d* is a constant and *b = O(n)*, we have a linear time complexity.
 
 use std::collections::VecDeque;
 
 use num::PrimInt;
 
-const BASE_10: u8 = 10;
+use crate::count_sort::count_sort_by_key_impl;
+use crate::probe::{NoProbe, Probe};
+use crate::select::min_max_by;
 
-/// There are two reference implementations:
-/// 1. Using count sort as a subroutine: https:///github.com/weihanglo/rust-algorithm-club/tree/master/src/sorting/radix_sort + https://brilliant.org/wiki/radix-sort/
-/// 2. Using buckets https:///github.com/myyrakle/buldak/blob/main/src/lib/radix.rs + https://blog.logrocket.com/radix-sort-no-comparisons-required/
-/// Ideas to make radix sort better:
-/// 1. more generic using traits
-/// 2. rebasing numbers in accordance to equations from CLRS
+/// Base used in CLRS examples and in the most of the tests.
+pub const BASE_10: u32 = 10;
 
-/// Radix sort implementation sorting primitive numbers in base 10.
+/// The largest accepted base, so 16-bit digits at most.
 ///
-/// Current implementations sorts `src` using bucket method. The idea is to create a bucket per digit of base 10 (i.e. one bucket for each of `0..base`)
-/// and sort numbers in `src` by each of their digits. Sorting by a digit using buckets simply means that we store a number in a bucket, which serves
-/// current sorting digit. For more explanation [see](https://blog.logrocket.com/radix-sort-no-comparisons-required/).
-pub fn radix_sort<T: PrimInt + Ord + Copy>(src: &mut [T]) {
-    let max_digits = count_max_digits(src);
-    let buckets = vec![VecDeque::<T>::new(); BASE_10 as usize];
-    let neg_buckets = vec![VecDeque::<T>::new(); BASE_10 as usize];
-    for _ in 0..max_digits {
+/// Both engines allocate *O(base)* buckets or counters before sorting, so the limit keeps them within a few MiB.
+pub const MAX_BASE: u32 = 1 << 16;
 
+// There are two reference implementations:
+// 1. Using count sort as a subroutine: https:///github.com/weihanglo/rust-algorithm-club/tree/master/src/sorting/radix_sort + https://brilliant.org/wiki/radix-sort/
+// 2. Using buckets https:///github.com/myyrakle/buldak/blob/main/src/lib/radix.rs + https://blog.logrocket.com/radix-sort-no-comparisons-required/
+// Ideas to make radix sort better:
+// 1. more generic using traits
+// 2. rebasing numbers in accordance to equations from CLRS
+
+/// Stable digit sorting procedure used by [radix_sort](fn.radix_sort.html) on each pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /// Distributes numbers to a bucket per digit and then collects them back to the input.
+    ///
+    /// Requires *O(n + base)* additional space for buckets.
+    Buckets,
+    /// Uses stable count sort (CLRS 8.3) keyed by the current digit as a subroutine.
+    ///
+    /// Requires *O(n + base)* additional space for counters and the sorted copy of the input.
+    CountSort,
+}
+
+/// Radix sort implementation sorting primitive numbers in base `base`.
+///
+/// Each pass sorts numbers in `src` by one of their digits using `engine`.
+///
+/// [Engine::Buckets](enum.Engine.html#variant.Buckets) creates a bucket per digit of base `base` (i.e. one bucket for each of `0..base`).
+/// Sorting by a digit using buckets simply means that we store a number in a bucket, which serves current sorting digit.
+/// For more explanation [see](https://blog.logrocket.com/radix-sort-no-comparisons-required/).
+/// Negative numbers are distributed to `neg_buckets` by the absolute value of their digit. While collecting numbers back to `src`, negative buckets
+/// are read in the reversed order (from `base - 1` down to `0`), so negative numbers are sorted by their absolute value in the non-increasing order,
+/// which is exactly the non-decreasing order for them. Then go non-negative numbers.
+///
+/// [Engine::CountSort](enum.Engine.html#variant.CountSort) does the same, but in terms of count sort keys: there are `2 * base` keys,
+/// first `base` of them are for negative digits in the reversed order, the last `base` are for non-negative digits.
+///
+/// Any `base` in `2..=MAX_BASE` is accepted: 10, 256, any power of two up to [MAX_BASE](constant.MAX_BASE.html) and so on.
+/// `base` may exceed the range of `T` (for example, 256 for `u8`), then numbers have at most two digits.
+///
+/// # Panics
+/// Panics if `base` is less than 2 or greater than [MAX_BASE](constant.MAX_BASE.html).
+pub fn radix_sort<T: PrimInt + Ord + Copy>(src: &mut [T], base: u32, engine: Engine) {
+    radix_sort_probed(src, base, engine, &NoProbe)
+}
+
+/// Same as [radix_sort](fn.radix_sort.html), but operations are reported to `probe`. Comparisons are made only to count digits.
+pub(crate) fn radix_sort_probed<T: PrimInt + Ord + Copy, P: Probe<T> + ?Sized>(src: &mut [T], base: u32, engine: Engine, probe: &P) {
+    assert!(base >= 2, "radix sort base should be at least 2");
+    assert!(base <= MAX_BASE, "radix sort base should be at most {}", MAX_BASE);
+    if src.len() <= 1 {
+        // `src` of length 1 is sorted
+        return;
+    }
+    let max_digits = count_max_digits(src, base, probe);
+    match engine {
+        Engine::Buckets => {
+            let mut buckets = vec![VecDeque::<T>::new(); base as usize];
+            let mut neg_buckets = vec![VecDeque::<T>::new(); base as usize];
+            for radix in 1..=max_digits as u32 {
+                buckets_pass(src, radix, base, &mut buckets, &mut neg_buckets, probe);
+            }
+        }
+        Engine::CountSort => {
+            let base_usize = base as usize;
+            let max_key = 2 * base_usize - 1;
+            for radix in 1..=max_digits as u32 {
+                count_sort_by_key_impl(
+                    src,
+                    max_key,
+                    |&num| {
+                        let digit = digit_abs(get_digit(num, radix, base));
+                        if num < T::zero() {
+                            base_usize - 1 - digit
+                        } else {
+                            base_usize + digit
+                        }
+                    },
+                    probe,
+                );
+            }
+        }
+    }
+}
+
+fn buckets_pass<T: PrimInt + Ord + Copy, P: Probe<T> + ?Sized>(
+    src: &mut [T],
+    radix: u32,
+    base: u32,
+    buckets: &mut [VecDeque<T>],
+    neg_buckets: &mut [VecDeque<T>],
+    probe: &P,
+) {
+    for &num in src.iter() {
+        let digit = digit_abs(get_digit(num, radix, base));
+        let bucket = if num < T::zero() { &mut neg_buckets[digit] } else { &mut buckets[digit] };
+        bucket.push_back(num);
+        probe.write(bucket.back().expect("the number is just pushed"));
+    }
+
+    let sorted_by_digit = neg_buckets.iter_mut().rev().chain(buckets.iter_mut()).flat_map(|bucket| bucket.drain(..));
+    for (slot, num) in src.iter_mut().zip(sorted_by_digit) {
+        *slot = num;
+        probe.write(slot);
     }
-    todo!()
 }
 
-fn count_max_digits<T: PrimInt + Ord + Copy>(src: &mut[T]) -> usize {
-    let mut max = src.iter().max().copied().expect("at least one element is in src");
-    let divisor = T::from(BASE_10).expect("BASE value suits any number type width");
-    let mut max_digits = 1;
-    // todo or we could do (max.to_f64().unwrap().log10() + 1) as usize
-    loop {
-        max = max / divisor;
-        if max == T::zero() {
-            break max_digits
+/// Absolute value of a digit, which is less than base, so it fits `usize`.
+///
+/// Digit of a negative number is non-positive, so it's converted through `i128` (`u128` digits are never negative).
+fn digit_abs<T: PrimInt>(digit: T) -> usize {
+    if digit < T::zero() {
+        digit.to_i128().map(i128::unsigned_abs).expect("negative digit fits i128") as usize
+    } else {
+        digit.to_usize().expect("digit is less than base")
+    }
+}
+
+/// Counts digits of the number with the largest absolute value in `src`.
+///
+/// Both minimum and maximum elements are checked, because the minimum one can be negative and have more digits than the maximum.
+fn count_max_digits<T: PrimInt + Ord + Copy, P: Probe<T> + ?Sized>(src: &[T], base: u32, probe: &P) -> usize {
+    let ((_, &min), (_, &max)) = min_max_by(src, |a, b| {
+        probe.compare(a, b);
+        a.cmp(b)
+    })
+    .expect("at least one element is in src");
+    let divisor = match T::from(base) {
+        Some(divisor) => divisor,
+        // all the numbers of `T` are less than `base` by absolute value except for `T::min_value()` of a signed `T`,
+        // which is `-base` at most (e.g. -128 for `i8` in base 128), and `-base` has 2 digits
+        None => return if widen(min) <= -i128::from(base) { 2 } else { 1 },
+    };
+    let count_digits = |mut num: T| {
+        let mut digits = 1;
+        // todo or we could do (max.to_f64().unwrap().log10() + 1) as usize
+        loop {
+            num = num / divisor;
+            if num == T::zero() {
+                break digits;
+            }
+            digits += 1;
+        }
+    };
+    count_digits(min).max(count_digits(max))
+}
+
+/// Returns `radix`'s digit of `num` in base `base`. Digits are numbered from 1 starting from the least significant one.
+///
+/// The digit of a negative number is non-positive (that's how `%` works in Rust).
+fn get_digit<T: PrimInt + Ord + Copy>(num: T, radix: u32, base: u32) -> T {
+    let divisor = match T::from(base) {
+        Some(divisor) => divisor,
+        None => {
+            let base = i128::from(base);
+            let digit = (1..radix).fold(widen(num), |num, _| num / base) % base;
+            return T::from(digit).expect("digit isn't greater than the number by absolute value");
         }
-        max_digits += 1;
+    };
+    match num::checked_pow(divisor, (radix - 1) as usize) {
+        Some(place) => num / place % divisor,
+        // `divisor.pow(radix - 1)` exceeds `T::max_value()`, which can happen only for the most significant digit of `T::min_value()`.
+        None => (1..radix).fold(num, |num, _| num / divisor) % divisor,
     }
 }
 
-// todo change name for `divisor`
-fn get_digit<T: PrimInt + Ord + Copy>(num: T, radix: u32) -> T {
-    let divisor = T::from(BASE_10).expect("BASE value suits any number type width");
-    num / divisor.pow(radix - 1) % divisor
+/// Converts `num` to `i128`. Used only when `base` exceeds `T::max_value()`, so `T` is narrower than `u32` and fits `i128`.
+fn widen<T: PrimInt>(num: T) -> i128 {
+    num.to_i128().expect("`T` is narrower than `u32`")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count_max_digits, get_digit};
+    use super::{count_max_digits, get_digit, radix_sort, Engine, BASE_10};
+    use crate::probe::NoProbe;
+    use crate::test_utils::get_test_vectors;
 
     #[test]
     fn test_digits_count() {
         // just some simple tests
-        assert_eq!(count_max_digits(&mut [123123]), 6);
-        assert_eq!(count_max_digits(&mut [0, 0, 0]), 1);
-        assert_eq!(count_max_digits(&mut [u128::MAX]), 39);
+        assert_eq!(count_max_digits(&[123123], BASE_10, &NoProbe), 6);
+        assert_eq!(count_max_digits(&[0, 0, 0], BASE_10, &NoProbe), 1);
+        assert_eq!(count_max_digits(&[u128::MAX], BASE_10, &NoProbe), 39);
+        assert_eq!(count_max_digits(&[-12345, 9], BASE_10, &NoProbe), 5);
+        assert_eq!(count_max_digits(&[255u8], 16, &NoProbe), 2);
+        assert_eq!(count_max_digits(&[255u8], 256, &NoProbe), 1);
+        assert_eq!(count_max_digits(&[i8::MIN], 2, &NoProbe), 8);
+        assert_eq!(count_max_digits(&[i8::MIN], 128, &NoProbe), 2);
+        assert_eq!(count_max_digits(&[i8::MIN + 1], 128, &NoProbe), 1);
     }
 
     #[test]
     fn test_get_digit() {
         // just some simple tests
-        assert_eq!(get_digit(123123, 1), 3);
-        assert_eq!(get_digit(123123, 3), 1);
-        assert_eq!(get_digit(193123, 5), 9);
-        assert_eq!(get_digit(0, 2), 0);
+        assert_eq!(get_digit(123123, 1, BASE_10), 3);
+        assert_eq!(get_digit(123123, 3, BASE_10), 1);
+        assert_eq!(get_digit(193123, 5, BASE_10), 9);
+        assert_eq!(get_digit(0, 2, BASE_10), 0);
+        assert_eq!(get_digit(-193123, 5, BASE_10), -9);
+        assert_eq!(get_digit(0xABu8, 2, 16), 0xA);
+        assert_eq!(get_digit(i8::MIN, 8, 2), -1);
+        assert_eq!(get_digit(i8::MIN, 1, 128), 0);
+        assert_eq!(get_digit(i8::MIN, 2, 128), -1);
+        assert_eq!(get_digit(i8::MAX, 1, 128), i8::MAX);
+    }
+
+    #[test]
+    fn radix_sort_test() {
+        for &engine in &[Engine::Buckets, Engine::CountSort] {
+            for &base in &[2, 3, BASE_10, 16, 256, 1 << 16] {
+                for (input, sorted) in get_test_vectors().iter_mut() {
+                    radix_sort(input, base, engine);
+                    assert_eq!(input, sorted);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn radix_sort_extremes_test() {
+        for &engine in &[Engine::Buckets, Engine::CountSort] {
+            radix_sort_extremes(engine);
+        }
+    }
+
+    fn radix_sort_extremes(engine: Engine) {
+        let mut src = [i8::MAX, 0, i8::MIN, -1, 1, i8::MIN + 1];
+        radix_sort(&mut src, 2, engine);
+        assert_eq!(src, [i8::MIN, i8::MIN + 1, -1, 0, 1, i8::MAX]);
+
+        let mut src = [i8::MAX, i8::MIN, -1, 1];
+        radix_sort(&mut src, 256, engine);
+        assert_eq!(src, [i8::MIN, -1, 1, i8::MAX]);
+
+        let mut src = [i8::MIN, 0, 5];
+        radix_sort(&mut src, 128, engine);
+        assert_eq!(src, [i8::MIN, 0, 5]);
+
+        let mut src = [5, i8::MAX, 0, i8::MIN, -1, i8::MIN + 1];
+        radix_sort(&mut src, 128, engine);
+        assert_eq!(src, [i8::MIN, i8::MIN + 1, -1, 0, 5, i8::MAX]);
+
+        let mut src = [i16::MAX, 0, i16::MIN, -300, i16::MIN + 1, 7];
+        radix_sort(&mut src, 1 << 15, engine);
+        assert_eq!(src, [i16::MIN, i16::MIN + 1, -300, 0, 7, i16::MAX]);
+
+        let mut src = [u8::MAX, 0, 17, 3, u8::MAX];
+        radix_sort(&mut src, 256, engine);
+        assert_eq!(src, [0, 3, 17, u8::MAX, u8::MAX]);
+
+        let mut src = [u128::MAX, 0, u128::MAX / 3, 42];
+        radix_sort(&mut src, BASE_10, engine);
+        assert_eq!(src, [0, 42, u128::MAX / 3, u128::MAX]);
+
+        let mut src = [i32::MIN, i32::MAX, 0, -1];
+        radix_sort(&mut src, super::MAX_BASE, engine);
+        assert_eq!(src, [i32::MIN, -1, 0, i32::MAX]);
+    }
+
+    #[test]
+    #[should_panic(expected = "radix sort base should be at most 65536")]
+    fn radix_sort_base_limit_test() {
+        radix_sort(&mut [3, 1, 2], super::MAX_BASE + 1, Engine::Buckets);
     }
-}
\ No newline at end of file
+}
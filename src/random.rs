@@ -0,0 +1,41 @@
+//! Small seedable pseudo random numbers generator.
+//!
+//! Randomized algorithms of the crate don't need cryptographic quality of random numbers, but they do need reproducible runs.
+//! So instead of depending on some crate we use [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator, which is a few lines of code.
+
+/// SplitMix64 generator. The same `seed` gives the same sequence of numbers.
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from `0..upper` range. Modulo bias is negligible for slices lengths.
+    pub(crate) fn gen_range(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+}
+
+#[test]
+fn rng_test() {
+    let mut rng1 = Rng::new(42);
+    let mut rng2 = Rng::new(42);
+    for _ in 0..100 {
+        let num = rng1.gen_range(10);
+        assert_eq!(num, rng2.gen_range(10));
+        assert!(num < 10);
+    }
+    assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+}
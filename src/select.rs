@@ -0,0 +1,281 @@
+//! Order statistics. Selecting the *k*-th smallest element in O(n) average time (CLRS 9.2).
+//!
+//! The simplest way to find the *k*-th smallest element, for example, the median, is to sort the input and to take the element at index *k*.
+//! But it's Ω(n*log n) work, most of which is useless: we don't need the order of elements other than the *k*-th one.
+//!
+//! Selection uses the same partitioning as [quick sort](../quick_sort/index.html). After partitioning we know in which of the two areas
+//! the *k*-th element is, so unlike quick sort we continue with only one of them. If the pivot is chosen randomly, the expected size
+//! of the area is a constant fraction of the input, so the expected time is *O(n + n/2 + n/4 + ...) = O(n)*. The worst case is still Θ(n^2).
+//!
+//! The worst case is avoided by choosing a good pivot deterministically (CLRS 9.3). The median of medians of groups of five elements has
+//! at least *3n/10 - 6* elements on each side, so the area we continue with shrinks by a constant fraction on each step, and
+//! the time is Θ(n) in the worst case.
+//!
+//! Minimum and maximum are order statistics as well (the first and the *n*-th). Each of them needs *n - 1* comparisons, but both of them
+//! can be found simultaneously in *3⌊n/2⌋* comparisons (CLRS 9.1).
+
+use std::cmp::Ordering;
+
+use crate::insertion_sort::insertion_sort_impl;
+use crate::probe::{self, NoProbe, Probe};
+use crate::quick_sort::{Partitioner, Scheme};
+
+/// Size of groups, medians of which are used to find the pivot in the worst-case linear selection.
+const GROUP_SIZE: usize = 5;
+
+/// Seed used by [select_nth](fn.select_nth.html), so its runs are reproducible.
+const SELECT_SEED: u64 = 0x5E1E_C7ED;
+
+/// Returns the `k`-th smallest element of `src` (counting from 0), i.e. the element, which would be at index `k` if `src` was sorted.
+///
+/// It's RANDOMIZED-SELECT from CLRS: [Partitioner::Randomized](../quick_sort/enum.Partitioner.html#variant.Randomized) with three-way
+/// partitioning scheme, so inputs with many duplicates are handled well.
+///
+/// Like `slice::select_nth_unstable`, `src` is left partitioned around `k`: elements of `src[..k]` are less or equal than `src[k]`,
+/// elements of `src[k + 1..]` are greater or equal than `src[k]`. So `src[..k]` are `k` smallest elements, though unordered.
+///
+/// # Panics
+/// Panics if `k >= src.len()`.
+pub fn select_nth<T: PartialOrd + Clone>(src: &mut [T], k: usize) -> &T {
+    select_nth_with(
+        src,
+        k,
+        Partitioner::Randomized {
+            scheme: Scheme::ThreeWay,
+            seed: SELECT_SEED,
+        },
+    )
+}
+
+/// Same as [select_nth](fn.select_nth.html), but partitioning is done by `partitioner`.
+///
+/// # Panics
+/// Panics if `k >= src.len()`.
+pub fn select_nth_with<T: PartialOrd + Clone>(src: &mut [T], k: usize, partitioner: Partitioner) -> &T {
+    assert!(k < src.len(), "k is {}, but src length is {}", k, src.len());
+    let mut rng = partitioner.rng();
+    // the `k`-th element is always in `src[start..end]`, elements before `start` are less or equal than it and elements after `end` are greater or equal
+    let mut start = 0;
+    let mut end = src.len();
+    while end - start > 1 {
+        let (end_left, start_right) = partitioner.partition(&mut src[start..end], &mut rng, &mut T::lt, &NoProbe);
+        if k < start + end_left {
+            end = start + end_left;
+        } else if k >= start + start_right {
+            start += start_right;
+        } else {
+            // `k` is in between areas, where the pivot (or all the elements equal to it) is already on its place
+            break;
+        }
+    }
+    &src[k]
+}
+
+/// Returns the `k`-th smallest element of `src` in Θ(n) time in the worst case. It's SELECT from CLRS.
+///
+/// `src` is left partitioned around `k` the same way as by [select_nth](fn.select_nth.html).
+/// Partitioning is done by three-way partitioning around the median of medians (see [median_of_medians](fn.median_of_medians.html)).
+/// CLRS assumes that elements are distinct, but elements equal to the pivot are kept in the middle area, which is excluded from the search,
+/// so the time stays linear with duplicates as well.
+///
+/// # Panics
+/// Panics if `k >= src.len()`.
+pub fn select_nth_deterministic<T: PartialOrd + Clone>(src: &mut [T], k: usize) -> &T {
+    assert!(k < src.len(), "k is {}, but src length is {}", k, src.len());
+    deterministic_select(src, k, &mut T::lt, &NoProbe);
+    &src[k]
+}
+
+/// Places the `k`-th smallest element of `src` to `src[k]` partitioning `src` around it.
+fn deterministic_select<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], k: usize, is_less: &mut F, probe: &P) {
+    let mut start = 0;
+    let mut end = src.len();
+    loop {
+        let area = &mut src[start..end];
+        if area.len() <= GROUP_SIZE {
+            insertion_sort_impl(area, is_less, probe);
+            return;
+        }
+        let pivot_idx = median_of_medians(area, is_less, probe);
+        let (end_left, start_right) = Scheme::ThreeWay.run_with_pivot(area, pivot_idx, is_less, probe);
+        if k < start + end_left {
+            end = start + end_left;
+        } else if k >= start + start_right {
+            start += start_right;
+        } else {
+            // `k` is in the area of elements equal to the pivot
+            return;
+        }
+    }
+}
+
+/// Returns the index of the median of medians of `src`.
+///
+/// `src` is divided into groups of five elements (the last group can be shorter). Each group is sorted by insertion sort, which takes
+/// constant time for five elements, and its median is moved to the beginning of `src`. Then the median of those *⌈n/5⌉* medians
+/// is found recursively by the worst-case linear selection.
+pub(crate) fn median_of_medians<T, F: FnMut(&T, &T) -> bool, P: Probe<T> + ?Sized>(src: &mut [T], is_less: &mut F, probe: &P) -> usize {
+    let groups = src.len().div_ceil(GROUP_SIZE);
+    for group in 0..groups {
+        let group_start = group * GROUP_SIZE;
+        let group_end = (group_start + GROUP_SIZE).min(src.len());
+        insertion_sort_impl(&mut src[group_start..group_end], is_less, probe);
+        // `group <= group_start`, so only already processed elements are overwritten
+        probe::swap(src, group, group_start + (group_end - group_start - 1) / 2, probe);
+    }
+    let median = (groups - 1) / 2;
+    deterministic_select(&mut src[..groups], median, is_less, probe);
+    median
+}
+
+/// Index of an extreme element of a slice and the reference to it.
+pub type Extremum<'a, T> = (usize, &'a T);
+
+/// Returns the minimum and the maximum elements of `src` along with their indices, or `None` if `src` is empty.
+///
+/// See [min_max_by](fn.min_max_by.html).
+pub fn min_max<T: Ord>(src: &[T]) -> Option<(Extremum<'_, T>, Extremum<'_, T>)> {
+    min_max_by(src, T::cmp)
+}
+
+/// Returns the minimum and the maximum elements of `src` with respect to `compare` along with their indices, or `None` if `src` is empty.
+///
+/// Elements are processed in pairs. Elements of a pair are compared with each other first, and then the smaller one is compared
+/// with the current minimum, and the larger one - with the current maximum. That's 3 comparisons for every 2 elements instead of 4,
+/// so there are at most *3⌊n/2⌋* comparisons in total.
+///
+/// If there are several equal minimum elements, the first one is returned. If there are several equal maximum elements, the last one
+/// is returned (the same way `Iterator::min_by` and `Iterator::max_by` do).
+pub fn min_max_by<T, F: FnMut(&T, &T) -> Ordering>(src: &[T], mut compare: F) -> Option<(Extremum<'_, T>, Extremum<'_, T>)> {
+    let (mut min_idx, mut max_idx, start) = match src.len() {
+        0 => return None,
+        // for odd length the first element is both minimum and maximum
+        len if len % 2 == 1 => (0, 0, 1),
+        _ if compare(&src[1], &src[0]) == Ordering::Less => (1, 0, 2),
+        _ => (0, 1, 2),
+    };
+    for pair_start in (start..src.len()).step_by(2) {
+        let (smaller, larger) = if compare(&src[pair_start + 1], &src[pair_start]) == Ordering::Less {
+            (pair_start + 1, pair_start)
+        } else {
+            (pair_start, pair_start + 1)
+        };
+        if compare(&src[smaller], &src[min_idx]) == Ordering::Less {
+            min_idx = smaller;
+        }
+        if compare(&src[larger], &src[max_idx]) != Ordering::Less {
+            max_idx = larger;
+        }
+    }
+    Some(((min_idx, &src[min_idx]), (max_idx, &src[max_idx])))
+}
+
+#[test]
+fn select_nth_test() {
+    use crate::test_utils::{get_random_vector, get_test_vectors};
+
+    let partitioners = [
+        Partitioner::Lomuto,
+        Partitioner::Hoare,
+        Partitioner::ThreeWay,
+        Partitioner::MedianOfThree(Scheme::Hoare),
+        Partitioner::Randomized {
+            scheme: Scheme::Lomuto,
+            seed: 1,
+        },
+    ];
+    for (input, sorted) in get_test_vectors().iter() {
+        for k in 0..input.len() {
+            for &partitioner in &partitioners {
+                let mut src = input.clone();
+                assert_eq!(*select_nth_with(&mut src, k, partitioner), sorted[k]);
+                assert!(src[..k].iter().all(|v| *v <= src[k]));
+                assert!(src[k + 1..].iter().all(|v| *v >= src[k]));
+            }
+            let mut src = input.clone();
+            assert_eq!(*select_nth(&mut src, k), sorted[k]);
+        }
+    }
+
+    let mut src = get_random_vector(100_001, 1_000, 5);
+    let mut sorted = src.clone();
+    sorted.sort_unstable();
+    let median = *select_nth(&mut src, 50_000);
+    assert_eq!(median, sorted[50_000]);
+    // bottom-k
+    src[..50_000].sort_unstable();
+    assert_eq!(src[..50_000], sorted[..50_000]);
+}
+
+#[test]
+#[should_panic]
+fn select_nth_out_of_bounds_test() {
+    select_nth(&mut [1, 2, 3], 3);
+}
+
+#[test]
+fn select_nth_deterministic_test() {
+    use crate::merge_sort::merge_sort;
+    use crate::test_utils::{get_random_vector, get_test_vectors};
+
+    for (input, sorted) in get_test_vectors().iter() {
+        for k in 0..input.len() {
+            let mut src = input.clone();
+            assert_eq!(*select_nth_deterministic(&mut src, k), sorted[k]);
+            assert!(src[..k].iter().all(|v| *v <= src[k]));
+            assert!(src[k + 1..].iter().all(|v| *v >= src[k]));
+        }
+    }
+
+    let n = 10_001;
+    let inputs = [
+        get_random_vector(n, u32::MAX, 3),
+        get_random_vector(n, 100, 4),
+        (0..n as u32).collect(),
+        (0..n as u32).rev().collect(),
+        // organ pipe
+        (0..n as u32).map(|i| i.min(n as u32 - i)).collect(),
+    ];
+    for input in inputs.iter() {
+        let mut sorted = input.clone();
+        merge_sort(&mut sorted);
+        for &k in &[0, 1, n / 3, n / 2, n - 2, n - 1] {
+            let mut src = input.clone();
+            assert_eq!(*select_nth_deterministic(&mut src, k), sorted[k]);
+        }
+    }
+
+    let mut src = [9, 1, 8, 2, 7, 3, 6, 4, 5, 0, 10, 11];
+    let median_idx = median_of_medians(&mut src, &mut i32::lt, &NoProbe);
+    // group medians are 7, 4 and 10, so the median of medians is 7
+    assert_eq!(src[median_idx], 7);
+}
+
+#[test]
+fn min_max_test() {
+    use crate::test_utils::get_test_vectors;
+
+    for (input, sorted) in get_test_vectors().iter() {
+        let mut comparisons = 0;
+        let result = min_max_by(input, |a, b| {
+            comparisons += 1;
+            a.cmp(b)
+        });
+        assert!(comparisons <= 3 * (input.len() / 2));
+        match result {
+            None => assert!(input.is_empty()),
+            Some(((min_idx, min), (max_idx, max))) => {
+                assert_eq!(Some(min), sorted.first());
+                assert_eq!(Some(max), sorted.last());
+                assert_eq!(input[min_idx], *min);
+                assert_eq!(input[max_idx], *max);
+            }
+        }
+    }
+
+    // the first minimum and the last maximum
+    assert_eq!(min_max(&[2, 1, 3, 1, 3]), Some(((1, &1), (4, &3))));
+    assert_eq!(min_max(&[5, 5, 5, 5]), Some(((0, &5), (3, &5))));
+    // reversed comparator swaps minimum and maximum
+    assert_eq!(min_max_by(&[2, 9, 4], |a, b| b.cmp(a)), Some(((1, &9), (0, &2))));
+}
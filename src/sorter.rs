@@ -0,0 +1,722 @@
+//! Common interface of the crate's sorting algorithms.
+//!
+//! Each module exposes a free function with its own signature: [quick_sort](../quick_sort/fn.quick_sort.html) needs a partitioner,
+//! [radix_sort](../radix_sort/fn.radix_sort.html) needs a base, [count_sort](../count_sort/fn.count_sort.html) works only with integers
+//! and can fail. [Sorter](trait.Sorter.html) hides those differences: algorithm parameters are stored in the sorter value, and errors
+//! are unified by [SortError](enum.SortError.html). Each sorter also describes the algorithm with [Metadata](struct.Metadata.html).
+//!
+//! Sorters can be instrumented: [Sorter::sort_with_probe](trait.Sorter.html#tymethod.sort_with_probe) reports operations of the algorithm
+//! to a [Probe](../probe/trait.Probe.html), and [Sorter::sort_with_stats](trait.Sorter.html#method.sort_with_stats) counts them.
+//!
+//! Registry functions ([comparison_sorters](fn.comparison_sorters.html), [integer_sorters](fn.integer_sorters.html) and
+//! [float_sorters](fn.float_sorters.html)) list all the sorters applicable to the element type, so tools and tests can iterate over them.
+//! Parallel sorters need `Send + Sync` elements, so they are listed separately by [parallel_sorters](fn.parallel_sorters.html).
+
+use std::error::Error;
+use std::fmt;
+
+use num::{Float, PrimInt};
+
+use crate::bubble_sort::{bubble_sort_lr, bubble_sort_lr_impl, bubble_sort_rl, bubble_sort_rl_impl};
+use crate::bucket_sort::{bucket_sort, bucket_sort_by_impl, BucketSortError};
+use crate::count_sort::{count_sort, count_sort_probed, CountSortError};
+use crate::heap_sort::{heap_sort, heap_sort_impl};
+use crate::insertion_sort::{insertion_sort, insertion_sort_impl};
+use crate::intro_sort::{intro_sort, intro_sort_with_limit};
+use crate::merge_sort::{merge_sort, merge_sort_bottom_up, merge_sort_bottom_up_impl, merge_sort_hybrid, merge_sort_impl, MERGE_SORT_THRESHOLD};
+use crate::natural_merge_sort::{natural_merge_sort, natural_merge_sort_impl};
+use crate::parallel::{par_merge_sort, par_quick_sort, Parallelism};
+use crate::probe::{probed_lt, Probe, SortStats, StatsProbe};
+use crate::quick_sort::{quick_sort, quick_sort_impl, quick_sort_tail_recursive, quick_sort_tail_recursive_impl, Partitioner, Scheme};
+use crate::radix_sort::{radix_sort, radix_sort_probed, Engine, BASE_10};
+
+/// Properties of a sorting algorithm.
+///
+/// Complexities are given in the same notation as in the modules docs, where *n* is the input length,
+/// *k* is the range of values and *d* is the number of digits in base *b*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub name: &'static str,
+    /// Elements with equal keys keep their relative order.
+    pub stable: bool,
+    /// Elements are rearranged within the input, i.e. no additional space proportional to *n* is used for elements.
+    pub in_place: bool,
+    pub best: &'static str,
+    pub average: &'static str,
+    pub worst: &'static str,
+    /// Additional space in the worst case, including the recursion stack.
+    pub space: &'static str,
+}
+
+/// Error of any sorter, which can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortError {
+    CountSort(CountSortError),
+    BucketSort(BucketSortError),
+    /// The sorter can't report its operations to a probe, e.g. a parallel one, because probes aren't thread-safe.
+    ProbeUnsupported,
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortError::CountSort(err) => write!(f, "count sort failed: {}", err),
+            SortError::BucketSort(err) => write!(f, "bucket sort failed: {}", err),
+            SortError::ProbeUnsupported => write!(f, "the sorter doesn't report operations to probes"),
+        }
+    }
+}
+
+impl Error for SortError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SortError::CountSort(err) => Some(err),
+            SortError::BucketSort(err) => Some(err),
+            SortError::ProbeUnsupported => None,
+        }
+    }
+}
+
+impl From<CountSortError> for SortError {
+    fn from(err: CountSortError) -> Self {
+        SortError::CountSort(err)
+    }
+}
+
+impl From<BucketSortError> for SortError {
+    fn from(err: BucketSortError) -> Self {
+        SortError::BucketSort(err)
+    }
+}
+
+/// Sorting algorithm of `T` elements.
+///
+/// `Debug` representation of a sorter shows its parameters, e.g. `QuickSort(Hoare)`, while [Metadata::name](struct.Metadata.html#structfield.name)
+/// is the name of the algorithm.
+pub trait Sorter<T>: fmt::Debug {
+    fn metadata(&self) -> Metadata;
+
+    /// Sorts `src` in the non-decreasing order. In case of error `src` stays untouched.
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError>;
+
+    /// Same as [sort](#tymethod.sort), but operations of the algorithm are reported to `probe`.
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError>;
+
+    /// Same as [sort](#tymethod.sort), but returns the numbers of operations performed by the algorithm.
+    fn sort_with_stats(&self, src: &mut [T]) -> Result<SortStats, SortError> {
+        let probe = StatsProbe::default();
+        self.sort_with_probe(src, &probe)?;
+        Ok(probe.stats())
+    }
+}
+
+/// See [bubble_sort_lr](../bubble_sort/fn.bubble_sort_lr.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BubbleSortLr;
+
+impl<T: PartialOrd + Clone> Sorter<T> for BubbleSortLr {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "bubble sort (left-right)",
+            stable: true,
+            in_place: true,
+            best: "Θ(n^2)",
+            average: "Θ(n^2)",
+            worst: "Θ(n^2)",
+            space: "O(1)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        bubble_sort_lr(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        bubble_sort_lr_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [bubble_sort_rl](../bubble_sort/fn.bubble_sort_rl.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BubbleSortRl;
+
+impl<T: PartialOrd + Clone> Sorter<T> for BubbleSortRl {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "bubble sort (right-left)",
+            ..<BubbleSortLr as Sorter<T>>::metadata(&BubbleSortLr)
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        bubble_sort_rl(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        bubble_sort_rl_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [insertion_sort](../insertion_sort/fn.insertion_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InsertionSort;
+
+impl<T: PartialOrd + Clone> Sorter<T> for InsertionSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "insertion sort",
+            stable: true,
+            in_place: true,
+            best: "Θ(n)",
+            average: "Θ(n^2)",
+            worst: "Θ(n^2)",
+            space: "O(1)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        insertion_sort(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        insertion_sort_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [merge_sort](../merge_sort/fn.merge_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeSort;
+
+impl<T: PartialOrd> Sorter<T> for MergeSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "merge sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n*log n)",
+            average: "Θ(n*log n)",
+            worst: "Θ(n*log n)",
+            space: "O(n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        merge_sort(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        merge_sort_impl(src, MERGE_SORT_THRESHOLD, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [merge_sort_hybrid](../merge_sort/fn.merge_sort_hybrid.html). The field is the insertion sort threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeSortHybrid(pub usize);
+
+impl<T: PartialOrd> Sorter<T> for MergeSortHybrid {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "hybrid merge sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n*log n)",
+            average: "Θ(n*log n)",
+            worst: "Θ(n*log n)",
+            space: "O(n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        merge_sort_hybrid(src, self.0);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        merge_sort_impl(src, self.0, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [merge_sort_bottom_up](../merge_sort/fn.merge_sort_bottom_up.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeSortBottomUp;
+
+impl<T: PartialOrd + Clone> Sorter<T> for MergeSortBottomUp {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "bottom-up merge sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n*log n)",
+            average: "Θ(n*log n)",
+            worst: "Θ(n*log n)",
+            space: "O(n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        merge_sort_bottom_up(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        merge_sort_bottom_up_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [natural_merge_sort](../natural_merge_sort/fn.natural_merge_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NaturalMergeSort;
+
+impl<T: PartialOrd> Sorter<T> for NaturalMergeSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "natural merge sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n)",
+            average: "O(n*log n)",
+            worst: "O(n*log n)",
+            space: "O(n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        natural_merge_sort(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        natural_merge_sort_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [heap_sort](../heap_sort/fn.heap_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapSort;
+
+impl<T: PartialOrd + Clone> Sorter<T> for HeapSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "heap sort",
+            stable: false,
+            in_place: true,
+            best: "O(n*log n)",
+            average: "O(n*log n)",
+            worst: "O(n*log n)",
+            space: "O(1)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        heap_sort(src);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        heap_sort_impl(src, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [quick_sort](../quick_sort/fn.quick_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuickSort(pub Partitioner);
+
+impl<T: PartialOrd + Clone> Sorter<T> for QuickSort {
+    fn metadata(&self) -> Metadata {
+        // Lomuto scheme puts all the elements equal to the pivot to one side, so all-equal input is still quadratic
+        let guaranteed = matches!(self.0, Partitioner::MedianOfMedians(scheme) if scheme != Scheme::Lomuto);
+        Metadata {
+            name: "quick sort",
+            stable: false,
+            in_place: true,
+            best: "O(n*log n)",
+            average: "O(n*log n)",
+            worst: if guaranteed { "Θ(n*log n)" } else { "Θ(n^2)" },
+            // the depth of recursion
+            space: if guaranteed { "O(log n)" } else { "O(n)" },
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        quick_sort(src, self.0);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        quick_sort_impl(src, self.0, &mut self.0.rng(), &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [quick_sort_tail_recursive](../quick_sort/fn.quick_sort_tail_recursive.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuickSortTailRecursive(pub Partitioner);
+
+impl<T: PartialOrd + Clone> Sorter<T> for QuickSortTailRecursive {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "tail recursive quick sort",
+            space: "O(log n)",
+            ..<QuickSort as Sorter<T>>::metadata(&QuickSort(self.0))
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        quick_sort_tail_recursive(src, self.0);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        quick_sort_tail_recursive_impl(src, self.0, &mut self.0.rng(), &mut probed_lt(probe), probe, 0);
+        Ok(())
+    }
+}
+
+/// See [intro_sort](../intro_sort/fn.intro_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntroSort(pub Partitioner);
+
+impl<T: PartialOrd + Clone> Sorter<T> for IntroSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "introsort",
+            stable: false,
+            in_place: true,
+            best: "O(n*log n)",
+            average: "O(n*log n)",
+            worst: "O(n*log n)",
+            space: "O(log n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        intro_sort(src, self.0);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        intro_sort_with_limit(src, self.0, &mut probed_lt(probe), probe);
+        Ok(())
+    }
+}
+
+/// See [par_merge_sort](../parallel/fn.par_merge_sort.html).
+///
+/// Probes aren't thread-safe, so [sort_with_probe](trait.Sorter.html#tymethod.sort_with_probe) returns
+/// [SortError::ProbeUnsupported](enum.SortError.html#variant.ProbeUnsupported).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParMergeSort(pub Parallelism);
+
+impl<T: PartialOrd + Send + Sync> Sorter<T> for ParMergeSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "parallel merge sort",
+            ..<MergeSort as Sorter<T>>::metadata(&MergeSort)
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        par_merge_sort(src, self.0);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, _src: &mut [T], _probe: &dyn Probe<T>) -> Result<(), SortError> {
+        Err(SortError::ProbeUnsupported)
+    }
+}
+
+/// See [par_quick_sort](../parallel/fn.par_quick_sort.html).
+///
+/// Probes aren't thread-safe, so [sort_with_probe](trait.Sorter.html#tymethod.sort_with_probe) returns
+/// [SortError::ProbeUnsupported](enum.SortError.html#variant.ProbeUnsupported).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParQuickSort {
+    pub partitioner: Partitioner,
+    pub parallelism: Parallelism,
+}
+
+impl<T: PartialOrd + Clone + Send> Sorter<T> for ParQuickSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "parallel quick sort",
+            ..<QuickSort as Sorter<T>>::metadata(&QuickSort(self.partitioner))
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        par_quick_sort(src, self.partitioner, self.parallelism);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, _src: &mut [T], _probe: &dyn Probe<T>) -> Result<(), SortError> {
+        Err(SortError::ProbeUnsupported)
+    }
+}
+
+/// See [count_sort](../count_sort/fn.count_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CountSort;
+
+impl<T: PrimInt> Sorter<T> for CountSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "count sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n + k)",
+            average: "Θ(n + k)",
+            worst: "Θ(n + k)",
+            space: "O(k)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        Ok(count_sort(src)?)
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        Ok(count_sort_probed(src, probe)?)
+    }
+}
+
+/// See [radix_sort](../radix_sort/fn.radix_sort.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RadixSort {
+    pub base: u32,
+    pub engine: Engine,
+}
+
+impl<T: PrimInt> Sorter<T> for RadixSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "radix sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(d * (n + b))",
+            average: "Θ(d * (n + b))",
+            worst: "Θ(d * (n + b))",
+            space: "O(n + b)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        radix_sort(src, self.base, self.engine);
+        Ok(())
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        radix_sort_probed(src, self.base, self.engine, probe);
+        Ok(())
+    }
+}
+
+/// See [bucket_sort](../bucket_sort/fn.bucket_sort.html). Values must be in the `[0, 1)` interval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BucketSort;
+
+impl<T: Float> Sorter<T> for BucketSort {
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            name: "bucket sort",
+            stable: true,
+            in_place: false,
+            best: "Θ(n)",
+            average: "Θ(n)",
+            worst: "Θ(n^2)",
+            space: "O(n)",
+        }
+    }
+
+    fn sort(&self, src: &mut [T]) -> Result<(), SortError> {
+        Ok(bucket_sort(src)?)
+    }
+
+    fn sort_with_probe(&self, src: &mut [T], probe: &dyn Probe<T>) -> Result<(), SortError> {
+        Ok(bucket_sort_by_impl(src, |value| value.to_f64().unwrap_or(f64::NAN), probe)?)
+    }
+}
+
+/// Returns comparison sorters, which can sort any `T` satisfying their bounds.
+///
+/// Quick sort based algorithms are listed with different partitioners.
+pub fn comparison_sorters<T: PartialOrd + Clone + 'static>() -> Vec<Box<dyn Sorter<T>>> {
+    let mut sorters: Vec<Box<dyn Sorter<T>>> = vec![
+        Box::new(BubbleSortLr),
+        Box::new(BubbleSortRl),
+        Box::new(InsertionSort),
+        Box::new(MergeSort),
+        Box::new(MergeSortHybrid(16)),
+        Box::new(MergeSortBottomUp),
+        Box::new(NaturalMergeSort),
+        Box::new(HeapSort),
+    ];
+    let partitioners = [
+        Partitioner::Lomuto,
+        Partitioner::Hoare,
+        Partitioner::ThreeWay,
+        Partitioner::Randomized {
+            scheme: Scheme::Hoare,
+            seed: 0,
+        },
+        Partitioner::MedianOfThree(Scheme::Hoare),
+        Partitioner::Ninther(Scheme::ThreeWay),
+        Partitioner::MedianOfMedians(Scheme::ThreeWay),
+    ];
+    for &partitioner in &partitioners {
+        sorters.push(Box::new(QuickSort(partitioner)));
+        sorters.push(Box::new(QuickSortTailRecursive(partitioner)));
+    }
+    sorters.push(Box::new(IntroSort(Partitioner::MedianOfThree(Scheme::Hoare))));
+    sorters
+}
+
+/// Returns all the comparison sorters along with count sort and radix sort, which work only with integers.
+pub fn integer_sorters<T: PrimInt + 'static>() -> Vec<Box<dyn Sorter<T>>> {
+    let mut sorters = comparison_sorters();
+    sorters.push(Box::new(CountSort));
+    for &engine in &[Engine::Buckets, Engine::CountSort] {
+        sorters.push(Box::new(RadixSort { base: BASE_10, engine }));
+        sorters.push(Box::new(RadixSort { base: 256, engine }));
+    }
+    sorters
+}
+
+/// Returns all the comparison sorters along with bucket sort, which works only with floating point values in the `[0, 1)` interval.
+pub fn float_sorters<T: Float + 'static>() -> Vec<Box<dyn Sorter<T>>> {
+    let mut sorters = comparison_sorters();
+    sorters.push(Box::new(BucketSort));
+    sorters
+}
+
+/// Returns parallel sorters with the default [Parallelism](../parallel/struct.Parallelism.html), which can sort any `Send + Sync` `T`
+/// satisfying their bounds.
+///
+/// They don't support probes, so [Sorter::sort_with_stats](trait.Sorter.html#method.sort_with_stats) fails for them.
+pub fn parallel_sorters<T: PartialOrd + Clone + Send + Sync + 'static>() -> Vec<Box<dyn Sorter<T>>> {
+    vec![
+        Box::new(ParMergeSort(Parallelism::default())),
+        Box::new(ParQuickSort {
+            partitioner: Partitioner::MedianOfThree(Scheme::Hoare),
+            parallelism: Parallelism::default(),
+        }),
+    ]
+}
+
+#[test]
+fn registry_test() {
+    use crate::test_utils::{get_random_vector, get_test_vectors};
+
+    for sorter in integer_sorters::<i32>() {
+        for (input, sorted) in get_test_vectors().iter_mut() {
+            assert!(sorter.sort(input).is_ok(), "{:?} failed", sorter);
+            assert_eq!(input, sorted, "{:?} failed", sorter);
+        }
+    }
+
+    let mut input: Vec<f64> = get_random_vector(1_000, 1_000, 1).into_iter().map(|v| f64::from(v) / 1_000.0).collect();
+    let mut sorted = input.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("no NaN values"));
+    for sorter in float_sorters::<f64>() {
+        let mut src = input.clone();
+        assert!(sorter.sort(&mut src).is_ok(), "{:?} failed", sorter);
+        assert_eq!(src, sorted, "{:?} failed", sorter);
+    }
+    input.push(f64::NAN);
+    assert_eq!(BucketSort.sort(&mut input), Err(SortError::BucketSort(BucketSortError::NaN(1_000))));
+    assert_eq!(
+        CountSort.sort(&mut [i64::MIN, i64::MAX]),
+        Err(SortError::CountSort(CountSortError::RangeTooLarge {
+            len: 2,
+            limit: crate::count_sort::MIN_RANGE_LIMIT
+        }))
+    );
+}
+
+#[test]
+fn parallel_sorters_test() {
+    use crate::test_utils::get_test_vectors;
+
+    // test vectors are short, so the parallelism is unlimited to divide them between threads
+    let parallelism = Parallelism { threads: 4, min_len: 0 };
+    let mut sorters = parallel_sorters::<i32>();
+    sorters.push(Box::new(ParMergeSort(parallelism)));
+    sorters.push(Box::new(ParQuickSort {
+        partitioner: Partitioner::Hoare,
+        parallelism,
+    }));
+    for sorter in sorters.iter() {
+        for (input, sorted) in get_test_vectors().iter() {
+            let mut src = input.clone();
+            assert!(sorter.sort(&mut src).is_ok());
+            assert_eq!(&src, sorted, "{:?} failed", sorter);
+
+            // probes aren't thread-safe, so operations aren't counted, and `src` stays untouched
+            let mut src = input.clone();
+            assert_eq!(sorter.sort_with_stats(&mut src), Err(SortError::ProbeUnsupported), "{:?}", sorter);
+            assert_eq!(&src, input);
+        }
+    }
+}
+
+#[test]
+fn quick_sort_metadata_test() {
+    let metadata = |partitioner| <QuickSort as Sorter<i32>>::metadata(&QuickSort(partitioner));
+    for &scheme in &[Scheme::Hoare, Scheme::ThreeWay] {
+        assert_eq!(metadata(Partitioner::MedianOfMedians(scheme)).worst, "Θ(n*log n)");
+        assert_eq!(metadata(Partitioner::MedianOfMedians(scheme)).space, "O(log n)");
+    }
+    assert_eq!(metadata(Partitioner::MedianOfMedians(Scheme::Lomuto)).worst, "Θ(n^2)");
+    assert_eq!(metadata(Partitioner::MedianOfMedians(Scheme::Lomuto)).space, "O(n)");
+    assert_eq!(metadata(Partitioner::Hoare).worst, "Θ(n^2)");
+}
+
+#[test]
+fn stability_metadata_test() {
+    use crate::test_utils::get_random_vector;
+
+    // records are compared only by `key`
+    #[derive(Clone, Debug, Default)]
+    struct Record {
+        key: u32,
+        idx: usize,
+    }
+
+    impl PartialEq for Record {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl PartialOrd for Record {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            self.key.partial_cmp(&other.key)
+        }
+    }
+
+    let input: Vec<Record> = get_random_vector(500, 10, 2)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, key)| Record { key, idx })
+        .collect();
+    for sorter in comparison_sorters::<Record>() {
+        let mut src = input.clone();
+        assert!(sorter.sort(&mut src).is_ok());
+        assert!(src.windows(2).all(|w| w[0].key <= w[1].key), "{:?} failed", sorter);
+        if sorter.metadata().stable {
+            let is_stable = src.windows(2).all(|w| w[0].key < w[1].key || w[0].idx < w[1].idx);
+            assert!(is_stable, "{:?} is marked as stable", sorter);
+        }
+    }
+}
@@ -10,6 +10,27 @@ pub(crate) fn test_sorting_algorithm(f: fn(&mut [i32])) -> Result<(), ()> {
     Ok(())
 }
 
+#[cfg(test)]
+type SortBy = fn(&mut [i32], fn(&i32, &i32) -> std::cmp::Ordering);
+
+#[cfg(test)]
+type SortByKey = fn(&mut [i32], fn(&i32) -> std::cmp::Reverse<i32>);
+
+/// Checks `_by` and `_by_key` variants of a sorting algorithm by sorting test vectors in the descending order.
+#[cfg(test)]
+pub(crate) fn test_sorting_algorithm_by(by: SortBy, by_key: SortByKey) -> Result<(), ()> {
+    for (input, sorted) in get_test_vectors().iter_mut() {
+        sorted.reverse();
+        let mut input_by_key = input.clone();
+        by(input, |a, b| b.cmp(a));
+        by_key(&mut input_by_key, |&v| std::cmp::Reverse(v));
+        if input != sorted || &input_by_key != sorted {
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 pub(crate) fn get_test_vectors() -> [(Vec<i32>, Vec<i32>); 22] {
     [
@@ -31,7 +52,7 @@ pub(crate) fn get_test_vectors() -> [(Vec<i32>, Vec<i32>); 22] {
         (vec![8, 3, 7, 9, 6, 1, 9, 10], vec![1, 3, 6, 7, 8, 9, 9, 10]),
         (vec![8, 2, 78, 892, 11, 0, 34], vec![0, 2, 8, 11, 34, 78, 892]),
         (
-            vec![9, 03, 83, 9, 2, 0, 1, 65, 2, 822, 9, 11, 22, 3, 3, 3, 47],
+            vec![9, 3, 83, 9, 2, 0, 1, 65, 2, 822, 9, 11, 22, 3, 3, 3, 47],
             vec![0, 1, 2, 2, 3, 3, 3, 3, 9, 9, 9, 11, 22, 47, 65, 83, 822],
         ),
         (vec![-6, 9, 0, 1, 17, 91, 0, 178], vec![-6, 0, 0, 1, 9, 17, 91, 178]),
@@ -40,3 +61,66 @@ pub(crate) fn get_test_vectors() -> [(Vec<i32>, Vec<i32>); 22] {
         (vec![2; 5], vec![2; 5]),
     ]
 }
+
+#[cfg(test)]
+pub(crate) fn get_random_vector(len: usize, max: u32, seed: u64) -> Vec<u32> {
+    let mut rng = crate::random::Rng::new(seed);
+    (0..len).map(|_| rng.gen_range(max as usize) as u32).collect()
+}
+
+/// Pairs each key with its index, so records with equal keys can be told apart to check stability.
+#[cfg(test)]
+pub(crate) fn get_records<I: IntoIterator<Item = u32>>(keys: I) -> Vec<(u32, usize)> {
+    keys.into_iter().enumerate().map(|(idx, key)| (key, idx)).collect()
+}
+
+/// Returns whether records are sorted by keys, and records with equal keys keep the order of their indices.
+#[cfg(test)]
+pub(crate) fn is_sorted_stably(records: &[(u32, usize)]) -> bool {
+    records.windows(2).all(|w| w[0].0 < w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1))
+}
+
+/// Element, which is neither `Clone` nor `Default`, and counts its drops, so tests can check that elements are neither lost, nor duplicated.
+#[cfg(test)]
+pub(crate) struct Droppable<'a> {
+    pub(crate) key: u32,
+    pub(crate) drops: &'a std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl Drop for Droppable<'_> {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Directory in the system temporary directory, which is removed with its contents on drop.
+#[cfg(test)]
+pub(crate) struct TempDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Creates an empty directory, `name` should be unique for the test.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("clrs-algos-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("temporary directory is created");
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    /// Returns the number of files in the directory.
+    pub(crate) fn files(&self) -> usize {
+        std::fs::read_dir(&self.0).expect("temporary directory is readable").count()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,481 @@
+//! Step-by-step execution traces of sorting algorithms, which can be animated.
+//!
+//! The module is available with the `trace` feature. [Tracer](struct.Tracer.html) is a [Probe](../probe/trait.Probe.html), which records
+//! operations of an algorithm as a stream of [events](enum.Event.html) in terms of positions in the input: `compare(i, j)`, `swap(i, j)`,
+//! `write(i, value)` and the steps specific for algorithms (partition boundaries, heapify sift steps and merged ranges).
+//! Operations on temporary buffers (for example, merge sort's one) are not recorded, only their results written back to the input.
+//!
+//! The trace is exported as [JSON Lines](https://jsonlines.org/) or as a sequence of SVG frames, one per event, which are bundled
+//! into a self-contained HTML page with a player. Sorts not traced use [NoProbe](../probe/struct.NoProbe.html), so they pay nothing.
+//!
+//! ```rust
+//! use clrs_algos::quick_sort::Partitioner;
+//! use clrs_algos::sorter::{QuickSort, Sorter};
+//! use clrs_algos::trace::Tracer;
+//!
+//! let mut src = vec![5, 1, 4, 2, 3];
+//! let tracer = Tracer::new(&src);
+//! QuickSort(Partitioner::Hoare).sort_with_probe(&mut src, &tracer).unwrap();
+//! assert_eq!(tracer.replay(), src);
+//! let json_lines = tracer.to_json_lines();
+//! let html = tracer.to_html();
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+
+use num::ToPrimitive;
+
+use crate::probe::Probe;
+
+/// Width of a bar representing an element in SVG frames.
+const BAR_WIDTH: usize = 12;
+/// Height of the tallest bar in SVG frames.
+const BARS_HEIGHT: usize = 200;
+/// Height of the caption area under the bars.
+const CAPTION_HEIGHT: usize = 24;
+/// Delay between frames in the HTML player.
+const FRAME_DELAY_MS: usize = 100;
+
+const DEFAULT_COLOR: &str = "#9b9b9b";
+const COMPARE_COLOR: &str = "#f5a623";
+const SWAP_COLOR: &str = "#d0021b";
+const WRITE_COLOR: &str = "#417505";
+const PIVOT_COLOR: &str = "#4a90e2";
+const SIFT_COLOR: &str = "#9013fe";
+const LEFT_AREA_COLOR: &str = "#dbe9f7";
+const RIGHT_AREA_COLOR: &str = "#f7e1db";
+
+/// Primitive step of a sorting algorithm. Indices are positions in the traced input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<T> {
+    /// Elements at `i` and `j` are compared.
+    Compare { i: usize, j: usize },
+    /// Elements at `i` and `j` are swapped.
+    Swap { i: usize, j: usize },
+    /// `value` is written to `i`, e.g. moved back from a temporary buffer or rebuilt from a key.
+    Write { i: usize, value: T },
+    /// `start..end` is partitioned into `start..end_left` and `start_right..end` areas, the pivot (or all the elements equal to it) is in between.
+    Partition {
+        start: usize,
+        end_left: usize,
+        start_right: usize,
+        end: usize,
+    },
+    /// Max heapify moves the element at `parent` down the heap by swapping it with its larger child at `child`.
+    Sift { parent: usize, child: usize },
+    /// Sorted `start..mid` and `mid..end` are going to be merged.
+    Merge { start: usize, mid: usize, end: usize },
+}
+
+impl<T: fmt::Display + ToPrimitive> Event<T> {
+    fn to_json(&self) -> String {
+        match self {
+            Event::Compare { i, j } => format!(r#"{{"event":"compare","i":{},"j":{}}}"#, i, j),
+            Event::Swap { i, j } => format!(r#"{{"event":"swap","i":{},"j":{}}}"#, i, j),
+            Event::Write { i, value } => format!(r#"{{"event":"write","i":{},"value":{}}}"#, i, json_value(value)),
+            Event::Partition {
+                start,
+                end_left,
+                start_right,
+                end,
+            } => format!(
+                r#"{{"event":"partition","start":{},"end_left":{},"start_right":{},"end":{}}}"#,
+                start, end_left, start_right, end
+            ),
+            Event::Sift { parent, child } => format!(r#"{{"event":"sift","parent":{},"child":{}}}"#, parent, child),
+            Event::Merge { start, mid, end } => format!(r#"{{"event":"merge","start":{},"mid":{},"end":{}}}"#, start, mid, end),
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Event<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Compare { i, j } => write!(f, "compare({}, {})", i, j),
+            Event::Swap { i, j } => write!(f, "swap({}, {})", i, j),
+            Event::Write { i, value } => write!(f, "write({}, {})", i, value),
+            Event::Partition {
+                start,
+                end_left,
+                start_right,
+                end,
+            } => write!(f, "partition({}..{}, {}..{})", start, end_left, start_right, end),
+            Event::Sift { parent, child } => write!(f, "sift({}, {})", parent, child),
+            Event::Merge { start, mid, end } => write!(f, "merge({}..{}, {}..{})", start, mid, mid, end),
+        }
+    }
+}
+
+/// Probe recording the trace of sorting the input, which it's created for.
+///
+/// Positions of elements are found by their addresses, so the tracer must be passed to the sort of the same slice, it was created with.
+/// Operations on other memory are ignored. The trace has an event per operation, so it's meant for small inputs.
+#[derive(Debug)]
+pub struct Tracer<T> {
+    initial: Vec<T>,
+    /// Address of the first element of the traced input.
+    base: usize,
+    events: RefCell<Vec<Event<T>>>,
+}
+
+impl<T: Clone> Tracer<T> {
+    /// Creates a tracer for sorting `src`. Values of `src` are copied, so the trace can be replayed.
+    pub fn new(src: &[T]) -> Self {
+        Tracer {
+            initial: src.to_vec(),
+            base: src.as_ptr() as usize,
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Values of the input before sorting.
+    pub fn initial(&self) -> &[T] {
+        &self.initial
+    }
+
+    /// Recorded events.
+    pub fn events(&self) -> Vec<Event<T>> {
+        self.events.borrow().clone()
+    }
+
+    /// Applies recorded swaps and writes to the initial values, so returns the input as it was left by the algorithm.
+    pub fn replay(&self) -> Vec<T> {
+        let mut values = self.initial.clone();
+        for event in self.events.borrow().iter() {
+            apply(&mut values, event);
+        }
+        values
+    }
+
+    /// Returns the position of `element` in the traced input.
+    fn index_of(&self, element: &T) -> Option<usize> {
+        let size = mem::size_of::<T>();
+        let address = element as *const T as usize;
+        if size == 0 || address < self.base {
+            return None;
+        }
+        let idx = (address - self.base) / size;
+        if idx < self.initial.len() {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    fn record(&self, event: Event<T>) {
+        self.events.borrow_mut().push(event);
+    }
+}
+
+impl<T: Clone + fmt::Display + ToPrimitive> Tracer<T> {
+    /// Exports the trace as JSON Lines. The first line is the `init` event with the initial values, then each event is a line
+    /// with its name in the `event` field and its parameters, e.g. `{"event":"swap","i":0,"j":3}`.
+    ///
+    /// Values are written as numbers, values not representable in JSON (NaN, infinities) are written as `null`.
+    pub fn to_json_lines(&self) -> String {
+        let values: Vec<String> = self.initial.iter().map(json_value).collect();
+        let mut lines = vec![format!(r#"{{"event":"init","values":[{}]}}"#, values.join(","))];
+        lines.extend(self.events.borrow().iter().map(Event::to_json));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Exports the trace as standalone SVG documents: the initial values and then the state after each event.
+    ///
+    /// Elements are drawn as bars. Elements of the event are highlighted, ranges of partitioning and merging are shaded.
+    pub fn to_svg_frames(&self) -> Vec<String> {
+        let scale = Scale::new(&self.initial);
+        let events = self.events.borrow();
+        let mut values = self.initial.clone();
+        let mut frames = vec![svg_frame(&values, None, &format!("0/{}: initial", events.len()), &scale)];
+        for (step, event) in events.iter().enumerate() {
+            apply(&mut values, event);
+            let caption = format!("{}/{}: {}", step + 1, events.len(), event);
+            frames.push(svg_frame(&values, Some(event), &caption, &scale));
+        }
+        frames
+    }
+
+    /// Exports the trace as a self-contained HTML page with SVG frames and a player to step through them or to play them.
+    pub fn to_html(&self) -> String {
+        let frames = self.to_svg_frames();
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Sorting trace</title>\n\
+             <style>.frame { display: none; } .frame.current { display: block; }</style>\n</head>\n<body>\n<div>\n\
+             <button id=\"prev\">&lt;</button>\n<button id=\"play\">play</button>\n<button id=\"next\">&gt;</button>\n",
+        );
+        html.push_str(&format!(
+            "<input id=\"step\" type=\"range\" min=\"0\" max=\"{}\" value=\"0\">\n</div>\n",
+            frames.len() - 1
+        ));
+        for (idx, frame) in frames.iter().enumerate() {
+            let class = if idx == 0 { "frame current" } else { "frame" };
+            html.push_str(&format!("<div class=\"{}\">{}</div>\n", class, frame));
+        }
+        html.push_str(&format!(
+            "<script>\n\
+             const frames = document.querySelectorAll('.frame');\n\
+             const step = document.getElementById('step');\n\
+             let current = 0;\n\
+             let timer = null;\n\
+             function show(idx) {{\n\
+             \x20   frames[current].classList.remove('current');\n\
+             \x20   current = Math.max(0, Math.min(frames.length - 1, idx));\n\
+             \x20   frames[current].classList.add('current');\n\
+             \x20   step.value = current;\n\
+             }}\n\
+             function stop() {{\n\
+             \x20   clearInterval(timer);\n\
+             \x20   timer = null;\n\
+             }}\n\
+             document.getElementById('prev').onclick = () => show(current - 1);\n\
+             document.getElementById('next').onclick = () => show(current + 1);\n\
+             step.oninput = () => show(Number(step.value));\n\
+             document.getElementById('play').onclick = () => {{\n\
+             \x20   if (timer !== null) {{\n\
+             \x20       stop();\n\
+             \x20       return;\n\
+             \x20   }}\n\
+             \x20   timer = setInterval(() => (current + 1 < frames.length ? show(current + 1) : stop()), {});\n\
+             }};\n\
+             </script>\n</body>\n</html>\n",
+            FRAME_DELAY_MS
+        ));
+        html
+    }
+}
+
+impl<T: Clone> Probe<T> for Tracer<T> {
+    fn compare(&self, a: &T, b: &T) {
+        if let (Some(i), Some(j)) = (self.index_of(a), self.index_of(b)) {
+            self.record(Event::Compare { i, j });
+        }
+    }
+
+    fn swap(&self, a: &T, b: &T) {
+        if let (Some(i), Some(j)) = (self.index_of(a), self.index_of(b)) {
+            self.record(Event::Swap { i, j });
+        }
+    }
+
+    fn write(&self, dst: &T) {
+        if let Some(i) = self.index_of(dst) {
+            self.record(Event::Write { i, value: dst.clone() });
+        }
+    }
+
+    fn partition(&self, src: &[T], end_left: usize, start_right: usize) {
+        if let Some(start) = src.first().and_then(|first| self.index_of(first)) {
+            self.record(Event::Partition {
+                start,
+                end_left: start + end_left,
+                start_right: start + start_right,
+                end: start + src.len(),
+            });
+        }
+    }
+
+    fn sift(&self, parent: &T, child: &T) {
+        if let (Some(parent), Some(child)) = (self.index_of(parent), self.index_of(child)) {
+            self.record(Event::Sift { parent, child });
+        }
+    }
+
+    fn merge(&self, src: &[T], mid: usize) {
+        if let Some(start) = src.first().and_then(|first| self.index_of(first)) {
+            self.record(Event::Merge {
+                start,
+                mid: start + mid,
+                end: start + src.len(),
+            });
+        }
+    }
+}
+
+fn apply<T: Clone>(values: &mut [T], event: &Event<T>) {
+    match event {
+        Event::Swap { i, j } => values.swap(*i, *j),
+        Event::Write { i, value } => values[*i] = value.clone(),
+        _ => {}
+    }
+}
+
+fn json_value<T: fmt::Display + ToPrimitive>(value: &T) -> String {
+    match value.to_f64() {
+        Some(float) if float.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+/// Maps values to heights of bars. The range always includes zero, so negative values are drawn below the zero line.
+struct Scale {
+    low: f64,
+    high: f64,
+}
+
+impl Scale {
+    fn new<T: ToPrimitive>(values: &[T]) -> Self {
+        let finite = values.iter().filter_map(ToPrimitive::to_f64).filter(|value| value.is_finite());
+        let (low, high) = finite.fold((0.0f64, 0.0f64), |(low, high), value| (low.min(value), high.max(value)));
+        Scale { low, high }
+    }
+
+    /// Returns the vertical coordinate of `value`.
+    fn y(&self, value: f64) -> f64 {
+        let span = if self.high > self.low { self.high - self.low } else { 1.0 };
+        let value = value.max(self.low).min(self.high);
+        BARS_HEIGHT as f64 * (self.high - value) / span
+    }
+}
+
+fn svg_frame<T: ToPrimitive>(values: &[T], event: Option<&Event<T>>, caption: &str, scale: &Scale) -> String {
+    let width = values.len().max(1) * BAR_WIDTH;
+    let height = BARS_HEIGHT + CAPTION_HEIGHT;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height
+    );
+    let (areas, bars) = event.map_or((vec![], vec![]), highlights);
+    for (start, end, color) in areas {
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            start * BAR_WIDTH,
+            (end - start) * BAR_WIDTH,
+            BARS_HEIGHT,
+            color
+        ));
+    }
+    let zero = scale.y(0.0);
+    for (idx, value) in values.iter().enumerate() {
+        let top = scale.y(value.to_f64().filter(|value| value.is_finite()).unwrap_or(0.0));
+        let color = bars.iter().rev().find(|(bar, _)| *bar == idx).map_or(DEFAULT_COLOR, |(_, color)| color);
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{:.1}\" width=\"{}\" height=\"{:.1}\" fill=\"{}\"/>",
+            idx * BAR_WIDTH + 1,
+            top.min(zero),
+            BAR_WIDTH - 2,
+            (top - zero).abs().max(1.0),
+            color
+        ));
+    }
+    svg.push_str(&format!(
+        "<text x=\"4\" y=\"{}\" font-family=\"monospace\" font-size=\"14\">{}</text></svg>",
+        height - 6,
+        caption
+    ));
+    svg
+}
+
+type Area = (usize, usize, &'static str);
+
+/// Returns shaded areas and highlighted bars of the frame showing `event`.
+fn highlights<T>(event: &Event<T>) -> (Vec<Area>, Vec<(usize, &'static str)>) {
+    match *event {
+        Event::Compare { i, j } => (vec![], vec![(i, COMPARE_COLOR), (j, COMPARE_COLOR)]),
+        Event::Swap { i, j } => (vec![], vec![(i, SWAP_COLOR), (j, SWAP_COLOR)]),
+        Event::Write { i, .. } => (vec![], vec![(i, WRITE_COLOR)]),
+        Event::Partition {
+            start,
+            end_left,
+            start_right,
+            end,
+        } => (
+            vec![(start, end_left, LEFT_AREA_COLOR), (start_right, end, RIGHT_AREA_COLOR)],
+            (end_left..start_right).map(|idx| (idx, PIVOT_COLOR)).collect(),
+        ),
+        Event::Sift { parent, child } => (vec![], vec![(parent, SIFT_COLOR), (child, SIFT_COLOR)]),
+        Event::Merge { start, mid, end } => (vec![(start, mid, LEFT_AREA_COLOR), (mid, end, RIGHT_AREA_COLOR)], vec![]),
+    }
+}
+
+#[test]
+fn insertion_sort_trace_test() {
+    use crate::sorter::{InsertionSort, Sorter};
+
+    let mut src = [3, 1, 2];
+    let tracer = Tracer::new(&src);
+    assert!(InsertionSort.sort_with_probe(&mut src, &tracer).is_ok());
+    let expected = [
+        Event::Compare { i: 1, j: 0 },
+        Event::Swap { i: 1, j: 0 },
+        Event::Compare { i: 2, j: 1 },
+        Event::Swap { i: 2, j: 1 },
+        Event::Compare { i: 1, j: 0 },
+    ];
+    assert_eq!(tracer.events(), expected);
+    assert_eq!(tracer.replay(), [1, 2, 3]);
+    assert_eq!(
+        tracer.to_json_lines(),
+        "{\"event\":\"init\",\"values\":[3,1,2]}\n\
+         {\"event\":\"compare\",\"i\":1,\"j\":0}\n\
+         {\"event\":\"swap\",\"i\":1,\"j\":0}\n\
+         {\"event\":\"compare\",\"i\":2,\"j\":1}\n\
+         {\"event\":\"swap\",\"i\":2,\"j\":1}\n\
+         {\"event\":\"compare\",\"i\":1,\"j\":0}\n"
+    );
+}
+
+#[test]
+fn sorters_trace_test() {
+    use crate::quick_sort::{Partitioner, Scheme};
+    use crate::sorter::{integer_sorters, HeapSort, IntroSort, MergeSort, QuickSort, Sorter};
+    use crate::test_utils::get_random_vector;
+
+    let input: Vec<i32> = get_random_vector(64, 100, 9).into_iter().map(|value| value as i32 - 50).collect();
+    // every sorter's trace replays to the sorted output, even if elements go through temporary buffers
+    for sorter in integer_sorters::<i32>() {
+        let mut src = input.clone();
+        let tracer = Tracer::new(&src);
+        assert!(sorter.sort_with_probe(&mut src, &tracer).is_ok());
+        assert_eq!(tracer.replay(), src, "{:?} failed", sorter);
+    }
+
+    let has_event = |sorter: &dyn Sorter<i32>, is_expected: fn(&Event<i32>) -> bool| {
+        let mut src = input.clone();
+        let tracer = Tracer::new(&src);
+        assert!(sorter.sort_with_probe(&mut src, &tracer).is_ok());
+        tracer.events().iter().any(is_expected)
+    };
+    assert!(has_event(&QuickSort(Partitioner::Hoare), |event| matches!(
+        event,
+        Event::Partition { start: 0, end: 64, .. }
+    )));
+    assert!(has_event(&IntroSort(Partitioner::Ninther(Scheme::Lomuto)), |event| matches!(
+        event,
+        Event::Partition { .. }
+    )));
+    assert!(has_event(&HeapSort, |event| matches!(event, Event::Sift { .. })));
+    assert!(has_event(&MergeSort, |event| *event == Event::Merge { start: 0, mid: 32, end: 64 }));
+    assert!(!has_event(&MergeSort, |event| matches!(event, Event::Partition { .. } | Event::Sift { .. })));
+}
+
+#[test]
+fn trace_export_test() {
+    use crate::sorter::{HeapSort, Sorter};
+
+    let mut src = [2.5, -1.0, 0.5, 4.0];
+    let tracer = Tracer::new(&src);
+    assert!(HeapSort.sort_with_probe(&mut src, &tracer).is_ok());
+    let events = tracer.events().len();
+
+    let json_lines = tracer.to_json_lines();
+    assert_eq!(json_lines.lines().count(), events + 1);
+    assert!(json_lines.starts_with("{\"event\":\"init\",\"values\":[2.5,-1,0.5,4]}\n"));
+    assert!(json_lines.lines().all(|line| line.starts_with("{\"event\":\"") && line.ends_with('}')));
+
+    let frames = tracer.to_svg_frames();
+    assert_eq!(frames.len(), events + 1);
+    assert!(frames.iter().all(|frame| frame.starts_with("<svg") && frame.ends_with("</svg>")));
+    assert!(frames[0].contains(&format!("0/{}: initial", events)));
+    assert!(frames[events].contains(SWAP_COLOR));
+
+    let html = tracer.to_html();
+    assert_eq!(html.matches("<div class=\"frame").count(), events + 1);
+    assert!(!html.contains("src=\""), "page is self-contained");
+
+    // NaN can't be written to JSON
+    assert_eq!(Tracer::new(&[f64::NAN, 1.0]).to_json_lines(), "{\"event\":\"init\",\"values\":[null,1]}\n");
+}